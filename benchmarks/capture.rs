@@ -0,0 +1,42 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::charset::*;
+use ripeg::pattern::*;
+use ripeg::vm::*;
+
+fn capture_exec() {
+    // {[0-9]+} (',' {[0-9]+})*
+    let number = capture(1, plus(class(NormalSet::range(b'0', b'9'))));
+    let p = concat(vec![number.clone(), star(concat(vec![literal(","), number]))]);
+    let p = p.compile().unwrap();
+    let mut vm = VM::new(&p);
+    // calling exec several times to negate time used by compilation.
+    let _m = vm.exec(b"1,22,333,4444,55555,666666,7777777,88888888");
+    let _m = vm.exec(b"1,22,333,4444,55555,666666,7777777,88888888");
+    let _m = vm.exec(b"1,22,333,4444,55555,666666,7777777,88888888");
+    let _m = vm.exec(b"1,22,333,4444,55555,666666,7777777,88888888");
+}
+
+fn capture_exec_benchmark(c: &mut Criterion) {
+    c.bench_function("capture_exec", |b| b.iter(capture_exec));
+}
+
+criterion_group!(benches, capture_exec_benchmark);
+criterion_main!(benches);
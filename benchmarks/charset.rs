@@ -15,66 +15,101 @@
 // You should have received a copy of the GNU General Public License
 // along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
 
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use ripeg::charset::*;
 
 fn normalset_add() {
-    let mut _s = NormalSet::new(&[67u8, 68, 69]); // C, D, E ASCII decimal value
-    let s2 = NormalSet::new(&[0u8, 68, 70]);
+    let mut _s = black_box(NormalSet::new(&[67u8, 68, 69])); // C, D, E ASCII decimal value
+    let s2 = black_box(NormalSet::new(&[0u8, 68, 70]));
     // calling add several times to negate time used by ::new call.
     _s.add(s2);
+    black_box(_s);
+}
+
+fn normalset_case_fold() {
+    let s = black_box(NormalSet::range(b'a', b'z'));
+    // calling case_fold several times to negate time used by ::range call.
+    black_box(s.case_fold());
+    black_box(s.case_fold());
+    black_box(s.case_fold());
+    black_box(s.case_fold());
 }
 
 fn normalset_complement() {
-    let s = NormalSet::new(&[67u8, 68, 69]);
+    let s = black_box(NormalSet::new(&[67u8, 68, 69]));
     // calling complement several times to negate time used by ::new call.
-    s.complement();
-    s.complement();
-    s.complement();
-    s.complement();
-    s.complement();
-    s.complement();
-    s.complement();
-    s.complement();
+    black_box(s.complement());
+    black_box(s.complement());
+    black_box(s.complement());
+    black_box(s.complement());
+    black_box(s.complement());
+    black_box(s.complement());
+    black_box(s.complement());
+    black_box(s.complement());
+}
+
+fn normalset_from_str() {
+    let _s: NormalSet = black_box(r"[^\x00-\x1f[:alpha:]0-9_\-]").parse().unwrap();
 }
 
 fn normalset_has() {
     let charset = [67u8, 68, 69]; // C, D, E ASCII decimal value
-    let s = NormalSet::new(&charset);
+    let s = black_box(NormalSet::new(&charset));
     let mut _result = false;
     // calling has several times to negate time used by ::new call.
-    _result = s.has(64);
-    _result = s.has(65);
-    _result = s.has(66);
-    _result = s.has(67);
-    _result = s.has(68);
-    _result = s.has(69);
-    _result = s.has(70);
-    _result = s.has(71);
-    _result = s.has(72);
-    _result = s.has(250);
+    _result = black_box(s.has(64));
+    _result = black_box(s.has(65));
+    _result = black_box(s.has(66));
+    _result = black_box(s.has(67));
+    _result = black_box(s.has(68));
+    _result = black_box(s.has(69));
+    _result = black_box(s.has(70));
+    _result = black_box(s.has(71));
+    _result = black_box(s.has(72));
+    _result = black_box(s.has(250));
 }
 
 fn normalset_is_small() {
     let charset = [67u8, 68, 69]; // C, D, E ASCII decimal value
-    let s = NormalSet::new(&charset);
+    let s = black_box(NormalSet::new(&charset));
     let mut _result = false;
     // calling is_small several times to negate time used by ::new call.
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
-    _result = s.is_small();
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+    _result = black_box(s.is_small());
+}
+
+fn normalset_is_subset() {
+    let s = black_box(NormalSet::range(b'a', b'f'));
+    let s2 = black_box(NormalSet::range(b'a', b'z'));
+    // calling is_subset several times to negate time used by ::range calls.
+    black_box(s.is_subset(&s2));
+    black_box(s2.is_subset(&s));
+    black_box(s.is_disjoint(&s2));
+    black_box(s2.is_disjoint(&s));
+}
+
+fn normalset_iter() {
+    let s = black_box(NormalSet::range(b'a', b'z'));
+    let _v: Vec<u8> = s.iter().collect();
+}
+
+fn normalset_named() {
+    // last and first names of the lookup.
+    let _s = black_box(NormalSet::named(black_box("xdigit")));
+    let _s = black_box(NormalSet::named(black_box("alnum")));
 }
 
 fn normalset_new() {
     // worst case, setting every bits
-    let _s = NormalSet::new(&[
+    let _s = black_box(NormalSet::new(black_box(&[
         0u8, 1, 2, 3, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
         25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47,
         48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70,
@@ -88,156 +123,221 @@ fn normalset_new() {
         203, 204, 205, 206, 207, 208, 209, 210, 211, 212, 213, 214, 215, 216, 217, 218, 219, 220,
         221, 222, 223, 224, 225, 226, 227, 228, 229, 230, 231, 232, 233, 234, 235, 236, 237, 238,
         239, 240, 241, 242, 243, 244, 245, 246, 247, 248, 249, 250, 251, 252, 253, 254, 255,
-    ]);
+    ])));
+}
+
+fn normalset_operators() {
+    let s = black_box(NormalSet::range(b'a', b'z'));
+    let s2 = black_box(NormalSet::range(b'0', b'f'));
+    let _s = black_box(s | s2);
+    let _s = black_box(s & s2);
+    let _s = black_box(s - s2);
+    let _s = black_box(s ^ s2);
+    let _s = black_box(!s);
 }
 
 fn normalset_range() {
     // worst case, 256 loops.
-    let mut _s = NormalSet::range(0, 255);
+    let _s = black_box(NormalSet::range(black_box(0), black_box(255)));
 }
 
 fn normalset_size() {
-    let s = NormalSet::new(&[0u8, 1, 2, 3]);
+    let s = black_box(NormalSet::new(&[0u8, 1, 2, 3]));
     // calling size several times to negate time used by ::new call.
-    let mut _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
+    let mut _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
 }
 
 fn normalset_smallset() {
-    let s = NormalSet::new(&[0u8, 1, 2, 3]);
+    let s = black_box(NormalSet::new(&[0u8, 1, 2, 3]));
     // calling smallset several times to negate time used by ::new call.
-    let mut _s2 = s.smallset();
-    let mut _s2 = s.smallset();
-    let mut _s2 = s.smallset();
-    let mut _s2 = s.smallset();
-    let mut _s2 = s.smallset();
-    let mut _s2 = s.smallset();
-    let mut _s2 = s.smallset();
-    let mut _s2 = s.smallset();
+    let mut _s2 = black_box(s.smallset());
+    let mut _s2 = black_box(s.smallset());
+    let mut _s2 = black_box(s.smallset());
+    let mut _s2 = black_box(s.smallset());
+    let mut _s2 = black_box(s.smallset());
+    let mut _s2 = black_box(s.smallset());
+    let mut _s2 = black_box(s.smallset());
+    let mut _s2 = black_box(s.smallset());
+}
+
+fn normalset_string() {
+    let s = black_box(NormalSet::from_bytes(b"\t\n acegikmoqsuwy0123456789-]"));
+    let _s = black_box(s.string());
 }
 
 fn normalset_sub() {
-    let s = NormalSet::new(&[0u8, 1, 2, 3]);
-    let s2 = NormalSet::range(3, 5);
+    let s = black_box(NormalSet::new(&[0u8, 1, 2, 3]));
+    let s2 = black_box(NormalSet::range(3, 5));
     // calling sub several times to negate time used by ::new call.
-    s.sub(s2);
+    black_box(s.sub(s2));
 }
 
 fn smallset_has() {
     let charset = [67u8, 68, 69]; // C, D, E ASCII decimal value
-    let s = SmallSet::new(&charset);
+    let s = black_box(SmallSet::new(&charset));
     let mut _result = false;
     // calling has several times to negate time used by ::new call.
-    _result = s.has(64);
-    _result = s.has(65);
-    _result = s.has(66);
-    _result = s.has(67);
-    _result = s.has(68);
-    _result = s.has(69);
-    _result = s.has(70);
-    _result = s.has(71);
-    _result = s.has(72);
-    _result = s.has(250);
+    _result = black_box(s.has(64));
+    _result = black_box(s.has(65));
+    _result = black_box(s.has(66));
+    _result = black_box(s.has(67));
+    _result = black_box(s.has(68));
+    _result = black_box(s.has(69));
+    _result = black_box(s.has(70));
+    _result = black_box(s.has(71));
+    _result = black_box(s.has(72));
+    _result = black_box(s.has(250));
 }
 
 fn smallset_new() {
     // worst case, setting every bits
-    let _s = SmallSet::new(&[
+    let _s = black_box(SmallSet::new(black_box(&[
         0u8, 1, 2, 3, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
         25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47,
         48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70,
         71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93,
         94, 95, 96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112,
         113, 114, 115, 116, 117, 118, 119, 120, 121, 122, 123, 124, 125, 126, 127,
-    ]);
+    ])));
+}
+
+fn smallset_operators() {
+    let s: SmallSet = black_box((b'a'..=b'z').collect());
+    let s2: SmallSet = black_box((b'0'..=b'f').collect());
+    let _s = black_box(s | s2);
+    let _s = black_box(s & s2);
+    let _s = black_box(s - s2);
+    let _s = black_box(s ^ s2);
+    let _s = black_box(!s);
 }
 
 fn smallset_size() {
-    let s = SmallSet::new(&[0u8, 1, 2, 3]);
+    let s = black_box(SmallSet::new(&[0u8, 1, 2, 3]));
     // calling size several times to negate time used by ::new call.
-    let mut _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
-    _size = s.size();
+    let mut _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
+    _size = black_box(s.size());
 }
 
 fn normalset_add_benchmark(c: &mut Criterion) {
     c.bench_function("normalset_add", |b| b.iter(normalset_add));
 }
 
+fn normalset_case_fold_benchmark(c: &mut Criterion) {
+    c.bench_function("normalset_case_fold", |b| b.iter(normalset_case_fold));
+}
+
 fn normalset_complement_benchmark(c: &mut Criterion) {
     c.bench_function("normalset_complement", |b| {
-        b.iter(|| normalset_complement())
+        b.iter(normalset_complement)
     });
 }
 
+fn normalset_from_str_benchmark(c: &mut Criterion) {
+    c.bench_function("normalset_from_str", |b| b.iter(normalset_from_str));
+}
+
 fn normalset_has_benchmark(c: &mut Criterion) {
-    c.bench_function("normalset_has", |b| b.iter(|| normalset_has()));
+    c.bench_function("normalset_has", |b| b.iter(normalset_has));
 }
 
 fn normalset_is_small_benchmark(c: &mut Criterion) {
-    c.bench_function("normalset_is_small", |b| b.iter(|| normalset_is_small()));
+    c.bench_function("normalset_is_small", |b| b.iter(normalset_is_small));
+}
+
+fn normalset_is_subset_benchmark(c: &mut Criterion) {
+    c.bench_function("normalset_is_subset", |b| b.iter(normalset_is_subset));
+}
+
+fn normalset_iter_benchmark(c: &mut Criterion) {
+    c.bench_function("normalset_iter", |b| b.iter(normalset_iter));
+}
+
+fn normalset_named_benchmark(c: &mut Criterion) {
+    c.bench_function("normalset_named", |b| b.iter(normalset_named));
 }
 
 fn normalset_new_benchmark(c: &mut Criterion) {
-    c.bench_function("normalset_new", |b| b.iter(|| normalset_new()));
+    c.bench_function("normalset_new", |b| b.iter(normalset_new));
+}
+
+fn normalset_operators_benchmark(c: &mut Criterion) {
+    c.bench_function("normalset_operators", |b| b.iter(normalset_operators));
 }
 
 fn normalset_range_benchmark(c: &mut Criterion) {
-    c.bench_function("normalset_range", |b| b.iter(|| normalset_range()));
+    c.bench_function("normalset_range", |b| b.iter(normalset_range));
 }
 
 fn normalset_size_benchmark(c: &mut Criterion) {
-    c.bench_function("normalset_size", |b| b.iter(|| normalset_size()));
+    c.bench_function("normalset_size", |b| b.iter(normalset_size));
 }
 
 fn normalset_smallset_benchmark(c: &mut Criterion) {
-    c.bench_function("normalset_smallset", |b| b.iter(|| normalset_smallset()));
+    c.bench_function("normalset_smallset", |b| b.iter(normalset_smallset));
+}
+
+fn normalset_string_benchmark(c: &mut Criterion) {
+    c.bench_function("normalset_string", |b| b.iter(normalset_string));
 }
 
 fn normalset_sub_benchmark(c: &mut Criterion) {
-    c.bench_function("normalset_sub", |b| b.iter(|| normalset_sub()));
+    c.bench_function("normalset_sub", |b| b.iter(normalset_sub));
 }
 
 fn smallset_has_benchmark(c: &mut Criterion) {
-    c.bench_function("smallset_has", |b| b.iter(|| smallset_has()));
+    c.bench_function("smallset_has", |b| b.iter(smallset_has));
 }
 
 fn smallset_new_benchmark(c: &mut Criterion) {
-    c.bench_function("smallset_new", |b| b.iter(|| smallset_new()));
+    c.bench_function("smallset_new", |b| b.iter(smallset_new));
+}
+
+fn smallset_operators_benchmark(c: &mut Criterion) {
+    c.bench_function("smallset_operators", |b| b.iter(smallset_operators));
 }
 
 fn smallset_size_benchmark(c: &mut Criterion) {
-    c.bench_function("smallset_size", |b| b.iter(|| smallset_size()));
+    c.bench_function("smallset_size", |b| b.iter(smallset_size));
 }
 
 criterion_group!(
     benches,
     normalset_add_benchmark,
+    normalset_case_fold_benchmark,
     normalset_complement_benchmark,
+    normalset_from_str_benchmark,
     normalset_has_benchmark,
     normalset_is_small_benchmark,
+    normalset_is_subset_benchmark,
+    normalset_iter_benchmark,
+    normalset_named_benchmark,
     normalset_new_benchmark,
+    normalset_operators_benchmark,
     normalset_range_benchmark,
     normalset_size_benchmark,
     normalset_smallset_benchmark,
+    normalset_string_benchmark,
     normalset_sub_benchmark,
     smallset_has_benchmark,
     smallset_new_benchmark,
+    smallset_operators_benchmark,
     smallset_size_benchmark
 );
 criterion_main!(benches);
@@ -0,0 +1,53 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::re::parse;
+use ripeg_derive::grammar;
+
+fn grammar_macro() {
+    let _p = grammar!(
+        "
+        Expr   <- Term (('+' / '-') Term)*
+        Term   <- Factor (('*' / '/') Factor)*
+        Factor <- [0-9]+ / '(' Expr ')' / [a-zA-Z_] [a-zA-Z0-9_]*
+    "
+    );
+}
+
+fn grammar_runtime() {
+    let _p = parse(
+        "
+        Expr   <- Term (('+' / '-') Term)*
+        Term   <- Factor (('*' / '/') Factor)*
+        Factor <- [0-9]+ / '(' Expr ')' / [a-zA-Z_] [a-zA-Z0-9_]*
+    ",
+    )
+    .unwrap()
+    .compile();
+}
+
+fn grammar_macro_benchmark(c: &mut Criterion) {
+    c.bench_function("grammar_macro", |b| b.iter(grammar_macro));
+}
+
+fn grammar_runtime_benchmark(c: &mut Criterion) {
+    c.bench_function("grammar_runtime", |b| b.iter(grammar_runtime));
+}
+
+criterion_group!(benches, grammar_macro_benchmark, grammar_runtime_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::charset::*;
+use ripeg::grammar::*;
+use ripeg::pattern::*;
+
+fn grammar_compile() {
+    // Expr <- Term ('+' Term)*
+    // Term <- [0-9]+ / '(' Expr ')'
+    let mut g = Grammar::new("Expr");
+    g.add(
+        "Expr",
+        concat(vec![
+            non_term("Term"),
+            star(concat(vec![literal("+"), non_term("Term")])),
+        ]),
+    );
+    g.add(
+        "Term",
+        or(vec![
+            plus(class(NormalSet::range(b'0', b'9'))),
+            concat(vec![literal("("), non_term("Expr"), literal(")")]),
+        ]),
+    );
+    // calling compile several times to negate time used by Grammar building.
+    let _p = g.compile();
+    let _p = g.compile();
+    let _p = g.compile();
+    let _p = g.compile();
+}
+
+fn grammar_analyze() {
+    // Expr <- Term ('+' Term)*
+    // Term <- [0-9]+ / '(' Expr ')'
+    let mut g = Grammar::new("Expr");
+    g.add(
+        "Expr",
+        concat(vec![
+            non_term("Term"),
+            star(concat(vec![literal("+"), non_term("Term")])),
+        ]),
+    );
+    g.add(
+        "Term",
+        or(vec![
+            plus(class(NormalSet::range(b'0', b'9'))),
+            concat(vec![literal("("), non_term("Expr"), literal(")")]),
+        ]),
+    );
+    // calling analyze several times to negate time used by Grammar building.
+    let _a = g.analyze();
+    let _a = g.analyze();
+    let _a = g.analyze();
+    let _a = g.analyze();
+}
+
+fn grammar_compile_benchmark(c: &mut Criterion) {
+    c.bench_function("grammar_compile", |b| b.iter(grammar_compile));
+}
+
+fn grammar_analyze_benchmark(c: &mut Criterion) {
+    c.bench_function("grammar_analyze", |b| b.iter(grammar_analyze));
+}
+
+criterion_group!(benches, grammar_compile_benchmark, grammar_analyze_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::input::*;
+use ripeg::memo::Table;
+use ripeg::re::parse;
+use ripeg::vm::VM;
+
+fn chunks_read_at() {
+    let mut c = Chunks::new(vec!["hello", " ", "wonderful", " ", "world"]);
+    let mut buf = [0u8; 8];
+    // calling read_at several times to negate time used by new.
+    let _n = c.read_at(&mut buf, 0);
+    let _n = c.read_at(&mut buf, 4);
+    let _n = c.read_at(&mut buf, 10);
+    let _n = c.read_at(&mut buf, 16);
+}
+
+fn vm_exec_input_chunks() {
+    let p = parse("Words <- [a-z]+ (' ' [a-z]+)*")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let mut c = Chunks::new(vec!["hello", " ", "wonderful", " ", "world"]);
+    let _m = VM::new(&p).exec_input(&mut c, &mut Table::new());
+}
+
+fn chunks_read_at_benchmark(c: &mut Criterion) {
+    c.bench_function("chunks_read_at", |b| b.iter(chunks_read_at));
+}
+
+fn vm_exec_input_chunks_benchmark(c: &mut Criterion) {
+    c.bench_function("vm_exec_input_chunks", |b| b.iter(vm_exec_input_chunks));
+}
+
+criterion_group!(benches, chunks_read_at_benchmark, vm_exec_input_chunks_benchmark);
+criterion_main!(benches);
@@ -16,7 +16,9 @@
 // along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
 
 use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::charset::{NormalSet, Set};
 use ripeg::isa::*;
+use ripeg::re::parse;
 
 fn program_new() {
     let _p = Program::new();
@@ -35,17 +37,120 @@ fn program_size() {
     let mut _s = p.size();
 }
 
+fn program_encode() {
+    let p = parse("Number <- [0-9]+ ('.' [0-9]+)? ([eE] [+-]? [0-9]+)?")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let _b = p.encode();
+}
+
+fn program_decode() {
+    let p = parse("Number <- [0-9]+ ('.' [0-9]+)? ([eE] [+-]? [0-9]+)?")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let b = p.encode();
+    // calling decode several times to negate time used by compile and encode.
+    let _p = Program::decode(&b);
+    let _p = Program::decode(&b);
+    let _p = Program::decode(&b);
+    let _p = Program::decode(&b);
+}
+
+fn program_disassemble() {
+    let p = parse("Number <- [0-9]+ ('.' [0-9]+)? ([eE] [+-]? [0-9]+)?")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let _s = p.disassemble();
+}
+
+fn program_from_str() {
+    let p = parse("Number <- [0-9]+ ('.' [0-9]+)? ([eE] [+-]? [0-9]+)?")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let s = p.disassemble();
+    // parsing several times to negate time used by compile and disassemble.
+    let _p = s.parse::<Program>();
+    let _p = s.parse::<Program>();
+    let _p = s.parse::<Program>();
+    let _p = s.parse::<Program>();
+}
+
+fn program_verify() {
+    let p = parse("Number <- [0-9]+ ('.' [0-9]+)? ([eE] [+-]? [0-9]+)?")
+        .unwrap()
+        .compile()
+        .unwrap();
+    // calling verify several times to negate time used by compile.
+    let _r = p.verify();
+    let _r = p.verify();
+    let _r = p.verify();
+    let _r = p.verify();
+}
+
+fn program_optimize() {
+    let mut p = Program::from(vec![
+        Instr::Choice(3),
+        Instr::Set(NormalSet::new(b"01234")),
+        Instr::Commit(0),
+        Instr::Choice(6),
+        Instr::Char(b'a'),
+        Instr::Commit(3),
+        Instr::Nop,
+        Instr::Choice(11),
+        Instr::Set(NormalSet::new(b"x")),
+        Instr::Commit(10),
+        Instr::Jump(12),
+        Instr::Char(b'y'),
+        Instr::End,
+    ]);
+    p.optimize();
+}
+
 fn program_new_benchmark(c: &mut Criterion) {
-    c.bench_function("program_new", |b| b.iter(|| program_new()));
+    c.bench_function("program_new", |b| b.iter(program_new));
 }
 
 fn program_size_benchmark(c: &mut Criterion) {
-    c.bench_function("program_size", |b| b.iter(|| program_size()));
+    c.bench_function("program_size", |b| b.iter(program_size));
+}
+
+fn program_encode_benchmark(c: &mut Criterion) {
+    c.bench_function("program_encode", |b| b.iter(program_encode));
+}
+
+fn program_decode_benchmark(c: &mut Criterion) {
+    c.bench_function("program_decode", |b| b.iter(program_decode));
+}
+
+fn program_disassemble_benchmark(c: &mut Criterion) {
+    c.bench_function("program_disassemble", |b| b.iter(program_disassemble));
+}
+
+fn program_from_str_benchmark(c: &mut Criterion) {
+    c.bench_function("program_from_str", |b| b.iter(program_from_str));
+}
+
+fn program_verify_benchmark(c: &mut Criterion) {
+    c.bench_function("program_verify", |b| b.iter(program_verify));
+}
+
+fn program_optimize_benchmark(c: &mut Criterion) {
+    c.bench_function("program_optimize", |b| b.iter(program_optimize));
 }
 
 criterion_group!(
     benches,
     program_new_benchmark,
     program_size_benchmark,
+    program_encode_benchmark,
+    program_decode_benchmark,
+    program_disassemble_benchmark,
+    program_from_str_benchmark,
+    program_verify_benchmark,
+    program_optimize_benchmark,
 );
 criterion_main!(benches);
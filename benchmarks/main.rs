@@ -6,6 +6,16 @@ macro_rules! mods {
 }
 
 mods![
+    mod capture;
     mod charset;
+    mod derive;
+    mod grammar;
+    mod input;
     mod isa;
+    mod memo;
+    mod pattern;
+    mod re;
+    mod scan;
+    mod unicode;
+    mod vm;
 ];
@@ -0,0 +1,87 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::memo::*;
+
+fn table_insert() {
+    let mut t = Table::new();
+    for pos in 0..64 {
+        t.insert(0, pos, Entry { length: Some(1), examined: 2, captures: Vec::new(), errors: Vec::new() });
+        t.insert(1, pos, Entry { length: None, examined: 1, captures: Vec::new(), errors: Vec::new() });
+    }
+}
+
+fn table_get() {
+    let mut t = Table::new();
+    t.insert(0, 10, Entry { length: Some(1), examined: 2, captures: Vec::new(), errors: Vec::new() });
+    t.insert(1, 10, Entry { length: None, examined: 1, captures: Vec::new(), errors: Vec::new() });
+    // calling get several times to negate time used by inserts.
+    let _e = t.get(0, 10);
+    let _e = t.get(1, 10);
+    let _e = t.get(0, 11);
+    let _e = t.get(1, 11);
+    let _e = t.get(0, 10);
+    let _e = t.get(1, 10);
+    let _e = t.get(0, 11);
+    let _e = t.get(1, 11);
+}
+
+fn table_remove() {
+    let mut t = Table::new();
+    for pos in 0..64 {
+        t.insert(0, pos, Entry { length: Some(1), examined: 2, captures: Vec::new(), errors: Vec::new() });
+    }
+    for pos in 0..64 {
+        let _e = t.remove(0, pos);
+    }
+}
+
+fn table_apply_edit() {
+    let mut t = Table::new();
+    for pos in 0..64 {
+        t.insert(0, pos, Entry { length: Some(1), examined: 2, captures: Vec::new(), errors: Vec::new() });
+    }
+    // insertion in the middle, moving half of the entries.
+    t.apply_edit(32, 0, 1);
+}
+
+fn table_insert_benchmark(c: &mut Criterion) {
+    c.bench_function("table_insert", |b| b.iter(table_insert));
+}
+
+fn table_get_benchmark(c: &mut Criterion) {
+    c.bench_function("table_get", |b| b.iter(table_get));
+}
+
+fn table_remove_benchmark(c: &mut Criterion) {
+    c.bench_function("table_remove", |b| b.iter(table_remove));
+}
+
+fn table_apply_edit_benchmark(c: &mut Criterion) {
+    c.bench_function("table_apply_edit", |b| b.iter(table_apply_edit));
+}
+
+criterion_group!(
+    benches,
+    table_insert_benchmark,
+    table_get_benchmark,
+    table_remove_benchmark,
+    table_apply_edit_benchmark
+);
+criterion_main!(benches);
@@ -0,0 +1,101 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::charset::*;
+use ripeg::pattern::*;
+
+fn pattern_compile() {
+    // [a-z]+ ('=' / ':') [0-9]* !.
+    let word = class(NormalSet::range(b'a', b'z'));
+    let p = concat(vec![
+        word.clone(),
+        star(word),
+        or(vec![literal("="), literal(":")]),
+        star(class(NormalSet::range(b'0', b'9'))),
+        not(any(1)),
+    ]);
+    // calling compile several times to negate time used by Pattern building.
+    let _p = p.compile();
+    let _p = p.compile();
+    let _p = p.compile();
+    let _p = p.compile();
+}
+
+fn pattern_analyze() {
+    // ([a-z]+ ('=' / ':') [0-9]* / '#' (!'\n' .)*)*
+    let word = class(NormalSet::range(b'a', b'z'));
+    let p = star(or(vec![
+        concat(vec![
+            plus(word),
+            or(vec![literal("="), literal(":")]),
+            star(class(NormalSet::range(b'0', b'9'))),
+        ]),
+        concat(vec![literal("#"), star(concat(vec![not(literal("\n")), any(1)]))]),
+    ]));
+    // calling analyze several times to negate time used by Pattern building.
+    let _a = p.analyze();
+    let _a = p.analyze();
+    let _a = p.analyze();
+    let _a = p.analyze();
+}
+
+fn pattern_literal_nocase() {
+    // 'select'i
+    let p = literal_nocase("select");
+    // calling compile several times to negate time used by Pattern building.
+    let _p = p.compile();
+    let _p = p.compile();
+    let _p = p.compile();
+    let _p = p.compile();
+}
+
+fn pattern_unicode() {
+    // [a-zà-ÿα-ω]
+    let p = unicode(&[('a', 'z'), ('à', 'ÿ'), ('α', 'ω')]);
+    // calling compile several times to negate time used by Pattern building.
+    let _p = p.compile();
+    let _p = p.compile();
+    let _p = p.compile();
+    let _p = p.compile();
+}
+
+fn pattern_compile_benchmark(c: &mut Criterion) {
+    c.bench_function("pattern_compile", |b| b.iter(pattern_compile));
+}
+
+fn pattern_analyze_benchmark(c: &mut Criterion) {
+    c.bench_function("pattern_analyze", |b| b.iter(pattern_analyze));
+}
+
+fn pattern_literal_nocase_benchmark(c: &mut Criterion) {
+    c.bench_function("pattern_literal_nocase", |b| b.iter(pattern_literal_nocase));
+}
+
+fn pattern_unicode_benchmark(c: &mut Criterion) {
+    c.bench_function("pattern_unicode", |b| b.iter(pattern_unicode));
+}
+
+criterion_group!(
+    benches,
+    pattern_compile_benchmark,
+    pattern_analyze_benchmark,
+    pattern_literal_nocase_benchmark,
+    pattern_unicode_benchmark
+);
+criterion_main!(benches);
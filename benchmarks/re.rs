@@ -0,0 +1,56 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::re::*;
+
+fn re_parse() {
+    let text = "
+        Expr   <- Term (('+' / '-') Term)*
+        Term   <- Factor (('*' / '/') Factor)*
+        Factor <- [0-9]+ / '(' Expr ')' / [a-zA-Z_] [a-zA-Z0-9_]*
+    ";
+    // calling parse several times to smooth measures.
+    let _g = parse(text);
+    let _g = parse(text);
+    let _g = parse(text);
+    let _g = parse(text);
+}
+
+fn re_parse_named() {
+    let text = "
+        Line  <- %s* Field (',' Field)* nl?
+        Field <- %d+ / [_%a] [_%w]* / %xdigit+
+        nl    <- [\n]
+    ";
+    let _g = parse(text);
+    let _g = parse(text);
+    let _g = parse(text);
+    let _g = parse(text);
+}
+
+fn re_parse_benchmark(c: &mut Criterion) {
+    c.bench_function("re_parse", |b| b.iter(re_parse));
+}
+
+fn re_parse_named_benchmark(c: &mut Criterion) {
+    c.bench_function("re_parse_named", |b| b.iter(re_parse_named));
+}
+
+criterion_group!(benches, re_parse_benchmark, re_parse_named_benchmark);
+criterion_main!(benches);
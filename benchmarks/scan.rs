@@ -0,0 +1,75 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ripeg::charset::*;
+use ripeg::scan::*;
+
+fn scan_new() {
+    let _s = black_box(Scanner::new(black_box(&NormalSet::WORD)));
+}
+
+fn scan_span_members(s: &Scanner, haystack: &[u8]) {
+    black_box(s.span(black_box(haystack)));
+}
+
+fn scan_span_short(s: &Scanner) {
+    // identifier-like spans, shorter than a vector.
+    black_box(s.span(black_box(b"id ")));
+    black_box(s.span(black_box(b"name_0 ")));
+    black_box(s.span(black_box(b"length = 1")));
+}
+
+fn scan_span_bitmap(s: &Scanner, haystack: &[u8]) {
+    black_box(s.span(black_box(haystack)));
+}
+
+fn scan_new_benchmark(c: &mut Criterion) {
+    c.bench_function("scan_new", |b| b.iter(scan_new));
+}
+
+fn scan_span_members_benchmark(c: &mut Criterion) {
+    // worst case, 4096 u8 spanned.
+    let s = Scanner::new(&NormalSet::BLANK);
+    let haystack = b" \t".repeat(2048);
+    c.bench_function("scan_span_members", |b| {
+        b.iter(|| scan_span_members(&s, &haystack))
+    });
+}
+
+fn scan_span_short_benchmark(c: &mut Criterion) {
+    let s = Scanner::new(&NormalSet::WORD);
+    c.bench_function("scan_span_short", |b| b.iter(|| scan_span_short(&s)));
+}
+
+fn scan_span_bitmap_benchmark(c: &mut Criterion) {
+    // worst case, 4096 u8 spanned.
+    let s = Scanner::new(&NormalSet::WORD);
+    let haystack = b"abcdefghijklmnopqrstuvwxyz_0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ".repeat(64);
+    c.bench_function("scan_span_bitmap", |b| {
+        b.iter(|| scan_span_bitmap(&s, &haystack))
+    });
+}
+
+criterion_group!(
+    benches,
+    scan_new_benchmark,
+    scan_span_members_benchmark,
+    scan_span_short_benchmark,
+    scan_span_bitmap_benchmark
+);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::unicode::*;
+
+fn unicode_case_fold() {
+    let letters = property("Ll").unwrap();
+    let _r = case_fold(&letters);
+}
+
+fn unicode_property() {
+    // calling property several times to smooth measures.
+    let _r = property("L");
+    let _r = property("Nd");
+    let _r = property("Greek");
+    let _r = property("Han");
+}
+
+fn unicode_utf8_sequences() {
+    let letters = property("L").unwrap();
+    let _s = utf8_sequences(&letters);
+}
+
+fn unicode_complement() {
+    let letters = property("L").unwrap();
+    let _r = complement(&letters);
+}
+
+fn unicode_case_fold_benchmark(c: &mut Criterion) {
+    c.bench_function("unicode_case_fold", |b| b.iter(unicode_case_fold));
+}
+
+fn unicode_property_benchmark(c: &mut Criterion) {
+    c.bench_function("unicode_property", |b| b.iter(unicode_property));
+}
+
+fn unicode_utf8_sequences_benchmark(c: &mut Criterion) {
+    c.bench_function("unicode_utf8_sequences", |b| b.iter(unicode_utf8_sequences));
+}
+
+fn unicode_complement_benchmark(c: &mut Criterion) {
+    c.bench_function("unicode_complement", |b| b.iter(unicode_complement));
+}
+
+criterion_group!(
+    benches,
+    unicode_case_fold_benchmark,
+    unicode_property_benchmark,
+    unicode_utf8_sequences_benchmark,
+    unicode_complement_benchmark
+);
+criterion_main!(benches);
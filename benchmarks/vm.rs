@@ -0,0 +1,88 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ripeg::charset::*;
+use ripeg::isa::*;
+use ripeg::re::parse;
+use ripeg::vm::*;
+
+fn vm_exec() {
+    // [a-z]+ ('=' / ':') [0-9]*
+    let p = Program::from(vec![
+        Instr::Set(NormalSet::range(b'a', b'z')),
+        Instr::Span(NormalSet::range(b'a', b'z')),
+        Instr::Choice(5),
+        Instr::Char(b'='),
+        Instr::Commit(6),
+        Instr::Char(b':'),
+        Instr::Span(NormalSet::range(b'0', b'9')),
+        Instr::End,
+    ]);
+    let mut vm = VM::new(&p);
+    // calling exec several times to negate time used by Program building.
+    let _m = vm.exec(b"abcdefghijklmnopqrstuvwxyz=0123456789");
+    let _m = vm.exec(b"abcdefghijklmnopqrstuvwxyz:0123456789");
+    let _m = vm.exec(b"abcdefghijklmnopqrstuvwxyz;0123456789");
+    let _m = vm.exec(b"0123456789");
+}
+
+fn vm_exec_left_recursion() {
+    let mut g = parse("Expr <- Expr^1 [+-] Expr^2 / Expr^2 [*/] Expr^3 / '(' Expr ')' / [0-9]+").unwrap();
+    g.left_recursion(true);
+    let p = g.compile().unwrap();
+    let mut vm = VM::new(&p);
+    // calling exec several times to negate time used by Program building.
+    let _m = vm.exec(b"1+2*3-4/5+(6-7)*8");
+    let _m = vm.exec(b"(1+2)*(3-4)/(5+6)-7*8");
+    let _m = vm.exec(b"1*2*3*4*5*6*7*8*9+1");
+    let _m = vm.exec(b"1+2+3+4+5+6+7+8+9*1");
+}
+
+fn vm_exec_span(vm: &mut VM, subject: &[u8]) {
+    let _m = vm.exec(subject);
+}
+
+fn vm_exec_benchmark(c: &mut Criterion) {
+    c.bench_function("vm_exec", |b| b.iter(vm_exec));
+}
+
+fn vm_exec_left_recursion_benchmark(c: &mut Criterion) {
+    c.bench_function("vm_exec_left_recursion", |b| b.iter(vm_exec_left_recursion));
+}
+
+fn vm_exec_span_benchmark(c: &mut Criterion) {
+    // log lines, dominated by long spans.
+    let g = parse(r"Log <- (' '* [^ \n]+ ' '+ [^\n]* [\n])* !.").unwrap();
+    let p = g.compile().unwrap();
+    let mut vm = VM::new(&p);
+    let subject = "    2022-11-05T10:31:07Z GET /static/assets/application-5f3e1b.css HTTP/1.1 200 \
+        18342 \"Mozilla/5.0 (X11; Linux x86_64; rv:106.0) Gecko/20100101 Firefox/106.0\"\n"
+        .repeat(64);
+    c.bench_function("vm_exec_span", |b| {
+        b.iter(|| vm_exec_span(&mut vm, subject.as_bytes()))
+    });
+}
+
+criterion_group!(
+    benches,
+    vm_exec_benchmark,
+    vm_exec_left_recursion_benchmark,
+    vm_exec_span_benchmark
+);
+criterion_main!(benches);
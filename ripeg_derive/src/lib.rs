@@ -0,0 +1,183 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! ripeg_derive. Procedural macros compiling ripeg grammars at build time.
+//!
+//! Grammars written in the syntax of [`ripeg::re`] are parsed and compiled while
+//! building the crate using them: syntax and compilation errors are reported as
+//! compiler errors, and the [`ripeg::isa::Program`] is a constant expression borrowing
+//! its instructions, usable in `const` and `static` items.
+
+// Any PR emitting warnings when it comes to documentation won’t be accepted.
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use ripeg::charset::NormalSet;
+use ripeg::isa::{Instr, Program};
+use ripeg::re;
+use std::collections::BTreeMap;
+use syn::{parse_macro_input, LitStr};
+
+/// Compiles a grammar text into a constant [`ripeg::isa::Program`] expression, see
+/// [`ripeg::isa::Program::from_static`].
+///
+/// The grammar is parsed with [`ripeg::re::parse`], its first rule being the start rule,
+/// and compiled with [`ripeg::grammar::Grammar::compile`]. Rule names are kept, see
+/// [`ripeg::isa::Program::rule_name`].
+///
+/// # Examples
+/// ```
+/// use ripeg::vm::VM;
+/// use ripeg_derive::grammar;
+/// let p = grammar!(r"
+///     Expr <- Term ('+' Term)*
+///     Term <- [0-9]+ / '(' Expr ')'
+/// ");
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"1+(2+30)+4").length, 10);
+/// assert_eq!(p.rule_name(1), Some("Term"));
+/// ```
+///
+/// The program can be a static, compiled once and shared by every [`ripeg::vm::VM`]:
+/// ```
+/// use ripeg::isa::Program;
+/// use ripeg::vm::VM;
+/// use ripeg_derive::grammar;
+/// static IDENT: Program = grammar!("Ident <- [_%a] [_%w]*");
+/// assert_eq!(VM::new(&IDENT).exec(b"snake_case2 = 1").length, 11);
+/// assert_eq!(IDENT.rule_name(0), Some("Ident"));
+/// ```
+///
+/// Grammar errors break the build:
+/// ```compile_fail
+/// use ripeg_derive::grammar;
+/// let p = grammar!("A <- [a-z");
+/// ```
+/// ```compile_fail
+/// use ripeg_derive::grammar;
+/// let p = grammar!("A <- B");
+/// ```
+#[proc_macro]
+pub fn grammar(input: TokenStream) -> TokenStream {
+    let text = parse_macro_input!(input as LitStr);
+    let program = re::parse(&text.value())
+        .map_err(|e| format!("grammar syntax error at {}", e))
+        .and_then(|g| {
+            g.compile()
+                .map_err(|e| format!("grammar compilation error: {}", e))
+        });
+    match program {
+        Ok(program) => program_tokens(&program),
+        Err(message) => syn::Error::new(text.span(), message).to_compile_error(),
+    }
+    .into()
+}
+
+/// Returns the tokens of an expression building program.
+fn program_tokens(program: &Program) -> TokenStream2 {
+    let mut code = Vec::new();
+    let mut names = BTreeMap::new();
+    let mut recovery = BTreeMap::new();
+    for index in 0..program.len() {
+        let instr = program.get(index).unwrap();
+        match instr {
+            Instr::Label(id) => {
+                if let Some(name) = program.rule_name(*id) {
+                    names.insert(*id, name);
+                }
+            }
+            Instr::Throw(label) => {
+                if let Some(rule) = program.recovery(*label) {
+                    recovery.insert(*label, rule);
+                }
+            }
+            _ => {}
+        }
+        code.push(instr_tokens(instr));
+    }
+    let names = names
+        .into_iter()
+        .map(|(id, name)| quote! { (#id, ::std::borrow::Cow::Borrowed(#name)) });
+    let recovery = recovery
+        .into_iter()
+        .map(|(label, rule)| quote! { (#label, #rule) });
+    quote! {
+        ::ripeg::isa::Program::from_static(
+            &[#(#code),*],
+            &[#(#names),*],
+            &[#(#recovery),*],
+        )
+    }
+}
+
+/// Returns the tokens of an expression building instr.
+fn instr_tokens(instr: &Instr) -> TokenStream2 {
+    match instr {
+        Instr::Any(n) => quote! { ::ripeg::isa::Instr::Any(#n) },
+        Instr::BackCommit(l) => quote! { ::ripeg::isa::Instr::BackCommit(#l) },
+        Instr::Call(l) => quote! { ::ripeg::isa::Instr::Call(#l) },
+        Instr::CaptureBegin(id) => quote! { ::ripeg::isa::Instr::CaptureBegin(#id) },
+        Instr::CaptureEnd => quote! { ::ripeg::isa::Instr::CaptureEnd },
+        Instr::Char(c) => quote! { ::ripeg::isa::Instr::Char(#c) },
+        Instr::Choice(l) => quote! { ::ripeg::isa::Instr::Choice(#l) },
+        Instr::Commit(l) => quote! { ::ripeg::isa::Instr::Commit(#l) },
+        Instr::End => quote! { ::ripeg::isa::Instr::End },
+        Instr::EndFail => quote! { ::ripeg::isa::Instr::EndFail },
+        Instr::Fail => quote! { ::ripeg::isa::Instr::Fail },
+        Instr::FailTwice => quote! { ::ripeg::isa::Instr::FailTwice },
+        Instr::Jump(l) => quote! { ::ripeg::isa::Instr::Jump(#l) },
+        Instr::Label(id) => quote! { ::ripeg::isa::Instr::Label(#id) },
+        Instr::LeftCall(l, k) => quote! { ::ripeg::isa::Instr::LeftCall(#l, #k) },
+        Instr::MemoClose => quote! { ::ripeg::isa::Instr::MemoClose },
+        Instr::MemoOpen(l, id) => quote! { ::ripeg::isa::Instr::MemoOpen(#l, #id) },
+        Instr::Nop => quote! { ::ripeg::isa::Instr::Nop },
+        Instr::PartialCommit(l) => quote! { ::ripeg::isa::Instr::PartialCommit(#l) },
+        Instr::Return => quote! { ::ripeg::isa::Instr::Return },
+        Instr::Set(s) => {
+            let s = set_tokens(s);
+            quote! { ::ripeg::isa::Instr::Set(#s) }
+        }
+        Instr::Span(s) => {
+            let s = set_tokens(s);
+            quote! { ::ripeg::isa::Instr::Span(#s) }
+        }
+        Instr::TestAny(n, l) => quote! { ::ripeg::isa::Instr::TestAny(#n, #l) },
+        Instr::TestChar(c, l) => quote! { ::ripeg::isa::Instr::TestChar(#c, #l) },
+        Instr::TestCharNoChoice(c, l) => {
+            quote! { ::ripeg::isa::Instr::TestCharNoChoice(#c, #l) }
+        }
+        Instr::TestSet(s, l) => {
+            let s = set_tokens(s);
+            quote! { ::ripeg::isa::Instr::TestSet(#s, #l) }
+        }
+        Instr::TestSetNoChoice(s, l) => {
+            let s = set_tokens(s);
+            quote! { ::ripeg::isa::Instr::TestSetNoChoice(#s, #l) }
+        }
+        Instr::Throw(label) => quote! { ::ripeg::isa::Instr::Throw(#label) },
+    }
+}
+
+/// Returns the tokens of a constant expression building set.
+fn set_tokens(set: &NormalSet) -> TokenStream2 {
+    let bits = set.bits;
+    quote! {
+        ::ripeg::charset::NormalSet { bits: [#(#bits),*] }
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! capture module provides the tree of captures returned by [`crate::vm::VM`] runs,
+//! built from [`crate::isa::Instr::CaptureBegin`] and [`crate::isa::Instr::CaptureEnd`].
+
+/// A capture: part of the subject matched between a [`crate::isa::Instr::CaptureBegin`]
+/// and its [`crate::isa::Instr::CaptureEnd`], and the captures nested inside.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// Capture ID, given by [`crate::isa::Instr::CaptureBegin`].
+    pub id: usize,
+    /// Position of the first u8 of the capture in the subject.
+    pub start: usize,
+    /// Position following the last u8 of the capture in the subject.
+    pub end: usize,
+    /// Nested captures, in subject order.
+    pub children: Vec<Node>,
+}
+
+/// Methods for a capture [`Node`]
+impl Node {
+    /// Returns the part of subject captured by a [`Node`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::capture::Node;
+    /// let n = Node { id: 0, start: 8, end: 10, children: Vec::new() };
+    /// assert_eq!(n.text(b"let x = 42;"), b"42");
+    /// ```
+    pub fn text<'s>(&self, subject: &'s [u8]) -> &'s [u8] {
+        &subject[self.start..self.end]
+    }
+
+    /// Returns a copy of a [`Node`] and its children moved from position from to position to.
+    pub(crate) fn moved(&self, from: usize, to: usize) -> Node {
+        Node {
+            id: self.id,
+            start: self.start - from + to,
+            end: self.end - from + to,
+            children: self.children.iter().map(|c| c.moved(from, to)).collect(),
+        }
+    }
+}
+
+/// Capture event recorded by the [`crate::vm::VM`] while matching.
+pub(crate) enum Event {
+    /// A capture with the given ID opens at a subject position.
+    Open(usize, usize),
+    /// The innermost open capture closes at a subject position.
+    Close(usize),
+    /// A complete capture, replayed from the [`crate::memo::Table`].
+    Node(Node),
+    /// An error label thrown at a subject position by [`crate::isa::Instr::Throw`].
+    Throw(usize, usize),
+}
+
+/// Builds the capture trees from a well-nested list of events.
+pub(crate) fn build(events: &[Event]) -> Vec<Node> {
+    // stack of open captures, the bottom one collecting top level nodes.
+    let mut open = vec![Node {
+        id: 0,
+        start: 0,
+        end: 0,
+        children: Vec::new(),
+    }];
+    for e in events {
+        match e {
+            Event::Open(id, pos) => open.push(Node {
+                id: *id,
+                start: *pos,
+                end: *pos,
+                children: Vec::new(),
+            }),
+            Event::Close(pos) => {
+                let mut node = open.pop().expect("capture closed without being opened");
+                node.end = *pos;
+                open.last_mut()
+                    .expect("capture closed without being opened")
+                    .children
+                    .push(node);
+            }
+            Event::Node(node) => open.last_mut().unwrap().children.push(node.clone()),
+            Event::Throw(..) => {}
+        }
+    }
+    open.swap_remove(0).children
+}
@@ -17,24 +17,105 @@
 
 //! charset module provides data types and methods for managing sets of characters.
 
-use std::ops::Not;
-
-use bitvec::prelude::*;
+use std::fmt;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
+};
+use std::str::FromStr;
 
 /// [`NormalSet`] structure represents a set of chars
 ///
-/// 256 bits, one for each possible character value. Little endian (Lsb0)
+/// 256 bits, one for each possible character value, in 4 words of 64 bits: character c
+/// is bit c % 64 of word c / 64. Sets are `Copy`, and can be built in constants with
+/// [`NormalSet::from_bytes`], [`NormalSet::range`], [`NormalSet::union`] and the other
+/// `const` methods.
+///
+/// Sets support the `|` (union), `&` (intersection), `-` (difference), `^` (symmetric
+/// difference) and `!` (complement) operators, and their assigning forms, on owned sets
+/// or references. They can be collected from, or extended with, u8.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::Set;
+/// use crate::ripeg::charset::NormalSet;
+/// let letters = NormalSet::range(b'a', b'z');
+/// let hex: NormalSet = b"0123456789abcdef".iter().copied().collect();
+/// assert_eq!((letters | hex).size(), 36);
+/// assert_eq!((letters & hex).size(), 6);
+/// assert_eq!((hex - letters).size(), 10);
+/// assert_eq!((letters ^ hex).size(), 30);
+/// assert_eq!((!&hex).size(), 240);
+/// let mut s = letters;
+/// s -= &hex;
+/// s.extend(*b"abcde");
+/// s |= NormalSet::new(&[b'f']);
+/// assert_eq!(s, letters);
+/// ```
+///
+/// Sets can be built at compile time:
+/// ```
+/// use crate::ripeg::charset::Set;
+/// use crate::ripeg::charset::NormalSet;
+/// const IDENT: NormalSet = NormalSet::range(b'a', b'z')
+///     .union(&NormalSet::range(b'A', b'Z'))
+///     .union(&NormalSet::from_bytes(b"_"));
+/// assert_eq!(IDENT.size(), 53);
+/// assert_eq!(IDENT.complement().size(), 203);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NormalSet {
-    /// 256 bits vector
-    pub bits: BitVec<u8, Lsb0>,
+    /// 256 bits, in 4 words of 64 bits
+    pub bits: [u64; 4],
+}
+
+/// Error returned when parsing a [`NormalSet`] with [`NormalSet::from_str`].
+#[derive(Debug)]
+pub struct Error {
+    /// Column of the error in u8, starting at 1.
+    pub column: usize,
+    /// Description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Formats a [`NormalSet`] as [`NormalSet::string`] does.
+impl fmt::Debug for NormalSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.string())
+    }
 }
 
 /// [`SmallSet`] structure represents only the ASCII set of chars
 ///
-/// 128 bits, one for each possible ASCII character value. Little endian (Lsb0)
+/// 128 bits, one for each possible ASCII character value, in 2 words of 64 bits:
+/// character c is bit c % 64 of word c / 64.
+///
+/// Sets support the same operators as [`NormalSet`], the complement being restricted to
+/// ASCII. u8 greater than 127 are ignored when building, collecting or extending a
+/// [`SmallSet`].
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::Set;
+/// use crate::ripeg::charset::SmallSet;
+/// let digits: SmallSet = (b'0'..=b'9').collect();
+/// let odd: SmallSet = [b'1', b'3', b'5', b'7', b'9', 255].iter().copied().collect();
+/// assert_eq!(odd.size(), 5);
+/// assert_eq!((digits - odd).size(), 5);
+/// assert!((digits ^ odd) | odd == digits);
+/// assert_eq!((!digits).size(), 118);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SmallSet {
-    /// 128 bits vector
-    pub bits: BitVec<u8, Lsb0>,
+    /// 128 bits, in 2 words of 64 bits
+    pub bits: [u64; 2],
 }
 
 /// [`Set`] trait defines common methods available for [`NormalSet`] and [`SmallSet`]
@@ -47,6 +128,134 @@ pub trait Set {
     fn size(&self) -> usize;
 }
 
+/// Implements the operators of the set algebra for a [`Set`] type whose bits are in a
+/// bits array of u64 words: assigning operators on owned and borrowed right-hand sides,
+/// binary operators on every owned and borrowed combination, complement, collection and
+/// extension.
+macro_rules! set_algebra {
+    ($set:ident) => {
+        impl BitOrAssign<&$set> for $set {
+            fn bitor_assign(&mut self, rhs: &$set) {
+                for (word, rhs) in self.bits.iter_mut().zip(rhs.bits) {
+                    *word |= rhs;
+                }
+            }
+        }
+
+        impl BitAndAssign<&$set> for $set {
+            fn bitand_assign(&mut self, rhs: &$set) {
+                for (word, rhs) in self.bits.iter_mut().zip(rhs.bits) {
+                    *word &= rhs;
+                }
+            }
+        }
+
+        impl BitXorAssign<&$set> for $set {
+            fn bitxor_assign(&mut self, rhs: &$set) {
+                for (word, rhs) in self.bits.iter_mut().zip(rhs.bits) {
+                    *word ^= rhs;
+                }
+            }
+        }
+
+        impl SubAssign<&$set> for $set {
+            fn sub_assign(&mut self, rhs: &$set) {
+                for (word, rhs) in self.bits.iter_mut().zip(rhs.bits) {
+                    *word &= !rhs;
+                }
+            }
+        }
+
+        impl Not for $set {
+            type Output = $set;
+            fn not(self) -> $set {
+                $set {
+                    bits: self.bits.map(|word| !word),
+                }
+            }
+        }
+
+        impl Not for &$set {
+            type Output = $set;
+            fn not(self) -> $set {
+                !*self
+            }
+        }
+
+        impl FromIterator<u8> for $set {
+            fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> $set {
+                let mut s = $set::new(&[]);
+                s.extend(iter);
+                s
+            }
+        }
+
+        impl Extend<u8> for $set {
+            fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+                for c in iter {
+                    if let Some(word) = self.bits.get_mut(c as usize / 64) {
+                        *word |= 1 << (c % 64);
+                    }
+                }
+            }
+        }
+
+        set_operator!($set, BitOr, bitor, BitOrAssign, bitor_assign);
+        set_operator!($set, BitAnd, bitand, BitAndAssign, bitand_assign);
+        set_operator!($set, BitXor, bitxor, BitXorAssign, bitxor_assign);
+        set_operator!($set, Sub, sub, SubAssign, sub_assign);
+    };
+}
+
+/// Implements a binary operator and its assigning operator on an owned right-hand side
+/// from its assigning operator on a borrowed one.
+macro_rules! set_operator {
+    ($set:ident, $op:ident, $method:ident, $assign:ident, $assign_method:ident) => {
+        impl $assign for $set {
+            fn $assign_method(&mut self, rhs: $set) {
+                self.$assign_method(&rhs);
+            }
+        }
+
+        impl $op for $set {
+            type Output = $set;
+            fn $method(mut self, rhs: $set) -> $set {
+                self.$assign_method(&rhs);
+                self
+            }
+        }
+
+        impl $op<&$set> for $set {
+            type Output = $set;
+            fn $method(mut self, rhs: &$set) -> $set {
+                self.$assign_method(rhs);
+                self
+            }
+        }
+
+        impl $op<$set> for &$set {
+            type Output = $set;
+            fn $method(self, rhs: $set) -> $set {
+                let mut s = *self;
+                s.$assign_method(&rhs);
+                s
+            }
+        }
+
+        impl $op for &$set {
+            type Output = $set;
+            fn $method(self, rhs: &$set) -> $set {
+                let mut s = *self;
+                s.$assign_method(rhs);
+                s
+            }
+        }
+    };
+}
+
+set_algebra!(NormalSet);
+set_algebra!(SmallSet);
+
 /// Common methods between [`SmallSet`] and [`NormalSet`]
 impl Set for SmallSet {
     /// Checks if a [`SmallSet`] contains a character
@@ -55,18 +264,13 @@ impl Set for SmallSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::SmallSet;
-    /// use bitvec::prelude::*;
     /// let s = SmallSet::new(&[67u8, 68, 69]); // C, D, E ASCII decimal value
     /// assert_eq!(s.has(66), false);
     /// assert_eq!(s.has(67), true);
     /// assert_eq!(s.has(250), false);
     /// ```
     fn has(&self, r: u8) -> bool {
-        if (r as usize) < self.bits.len() {
-            self.bits[r as usize]
-        } else {
-            false
-        }
+        r < 128 && self.bits[r as usize / 64] >> (r % 64) & 1 != 0
     }
 
     /// Instanciate a [`SmallSet`] with a given charset
@@ -75,19 +279,12 @@ impl Set for SmallSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::SmallSet;
-    /// use bitvec::prelude::*;
     /// let s = SmallSet::new(&[67u8, 68, 69]); // C, D, E ASCII decimal value
     /// assert_eq!(s.has(66), false);
     /// assert_eq!(s.has(67), true);
     /// ```
     fn new(chars: &[u8]) -> Self {
-        let mut s = Self {
-            bits: bitvec![u8, Lsb0; 0; 128],
-        };
-        for i in chars {
-            s.bits.set(*i as usize, true);
-        }
-        s
+        Self::from_bytes(chars)
     }
 
     /// Count number of bits with value 1 in the bit vector which is the number of characters matched by a [`SmallSet`]
@@ -96,12 +293,96 @@ impl Set for SmallSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::SmallSet;
-    /// use bitvec::prelude::*;
     /// let s = SmallSet::new(&[67u8, 68, 69]); // C, D, E ASCII decimal value
     /// assert_eq!(s.size(), 3);
     /// ```
     fn size(&self) -> usize {
-        self.bits.count_ones()
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
+
+/// Implementations of functions reserved to [`SmallSet`]
+impl SmallSet {
+    /// Checks if every character of a [`SmallSet`] is contained in other
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::SmallSet;
+    /// let s = SmallSet::new(&[67u8, 68]); // C, D ASCII decimal value
+    /// let s2 = SmallSet::new(&[67u8, 68, 69]); // C, D, E ASCII decimal value
+    /// assert!(s.is_subset(&s2));
+    /// assert!(!s2.is_subset(&s));
+    /// assert!(s2.is_superset(&s));
+    /// ```
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.bits.iter().zip(other.bits).all(|(a, b)| a & !b == 0)
+    }
+
+    /// Checks if a [`SmallSet`] contains every character of other
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::SmallSet;
+    /// let s = SmallSet::new(&[67u8, 68, 69]); // C, D, E ASCII decimal value
+    /// assert!(s.is_superset(&SmallSet::new(&[67u8, 69])));
+    /// assert!(!s.is_superset(&SmallSet::new(&[66u8, 67])));
+    /// ```
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Checks if a [`SmallSet`] and other have no character in common
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::SmallSet;
+    /// let s = SmallSet::new(&[67u8, 68]); // C, D ASCII decimal value
+    /// assert!(s.is_disjoint(&SmallSet::new(&[69u8])));
+    /// assert!(!s.is_disjoint(&SmallSet::new(&[68u8, 69])));
+    /// ```
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.bits.iter().zip(other.bits).all(|(a, b)| a & b == 0)
+    }
+
+    /// Returns an iterator over the characters of a [`SmallSet`], in increasing order
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::SmallSet;
+    /// let s = SmallSet::new(&[69u8, 67, 68]); // E, C, D ASCII decimal value
+    /// assert_eq!(s.iter().collect::<Vec<u8>>(), b"CDE");
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..128u8).filter(move |c| self.has(*c))
+    }
+
+    /// Instanciate a [`SmallSet`] with a given charset, u8 greater than 127 being ignored;
+    /// unlike [`Set::new`], usable in constants
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::SmallSet;
+    /// const VOWELS: SmallSet = SmallSet::from_bytes(b"aeiouy\xff");
+    /// assert_eq!(VOWELS.size(), 6);
+    /// ```
+    pub const fn from_bytes(chars: &[u8]) -> SmallSet {
+        let mut bits = [0; 2];
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] < 128 {
+                bits[chars[i] as usize / 64] |= 1 << (chars[i] % 64);
+            }
+            i += 1;
+        }
+        SmallSet { bits }
     }
 }
 
@@ -113,7 +394,6 @@ impl Set for NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::NormalSet;
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::new(&[67u8, 68, 69, 247]); // C, D, E, ÷ ASCII decimal value
     /// assert_eq!(s.has(66), false);
     /// assert_eq!(s.has(67), true);
@@ -121,7 +401,7 @@ impl Set for NormalSet {
     /// assert_eq!(s.has(247), true);
     /// ```
     fn has(&self, r: u8) -> bool {
-        self.bits[r as usize]
+        self.bits[r as usize / 64] >> (r % 64) & 1 != 0
     }
 
     /// Instanciate [`NormalSet`] with a given charset
@@ -130,7 +410,6 @@ impl Set for NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::NormalSet;
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::new(&[67u8, 68, 69, 247]); // C, D, E, ÷ ASCII decimal value
     /// assert_eq!(s.has(66), false);
     /// assert_eq!(s.has(67), true);
@@ -138,13 +417,7 @@ impl Set for NormalSet {
     /// assert_eq!(s.has(247), true);
     /// ```
     fn new(chars: &[u8]) -> Self {
-        let mut s = Self {
-            bits: bitvec![u8, Lsb0; 0; 256],
-        };
-        for i in chars {
-            s.bits.set(*i as usize, true);
-        }
-        s
+        Self::from_bytes(chars)
     }
 
     /// Count number of bits with value 1 in the bits vector which is the number of characters matched by [`NormalSet`]
@@ -153,31 +426,98 @@ impl Set for NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::NormalSet;
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::new(&[67u8, 68, 69, 247]); // C, D, E, ÷ ASCII decimal value
     /// assert_eq!(s.size(), 4);
     /// ```
     fn size(&self) -> usize {
-        self.bits.count_ones()
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
     }
 }
 
 /// Implementations of functions reserved to [`NormalSet`]
 impl NormalSet {
+    /// Checks if every character of a [`NormalSet`] is contained in other
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// let s = NormalSet::new(&[67u8, 247]); // C, ÷ ASCII decimal value
+    /// let s2 = NormalSet::new(&[67u8, 68, 247]); // C, D, ÷ ASCII decimal value
+    /// assert!(s.is_subset(&s2));
+    /// assert!(!s2.is_subset(&s));
+    /// assert!(s2.is_superset(&s));
+    /// ```
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.bits.iter().zip(other.bits).all(|(a, b)| a & !b == 0)
+    }
+
+    /// Checks if a [`NormalSet`] contains every character of other
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// let s = NormalSet::range(67, 247);
+    /// assert!(s.is_superset(&NormalSet::new(&[67u8, 247])));
+    /// assert!(!s.is_superset(&NormalSet::new(&[66u8, 67])));
+    /// ```
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Checks if a [`NormalSet`] and other have no character in common
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// let s = NormalSet::new(&[67u8, 247]); // C, ÷ ASCII decimal value
+    /// assert!(s.is_disjoint(&NormalSet::range(68, 246)));
+    /// assert!(!s.is_disjoint(&NormalSet::range(68, 247)));
+    /// ```
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.bits.iter().zip(other.bits).all(|(a, b)| a & b == 0)
+    }
+
     /// Adds a [`NormalSet`] to the existing one (binary OR operation)
     ///
     /// # Examples
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::NormalSet;
-    /// use bitvec::prelude::*;
     /// let mut s = NormalSet::new(&[67u8, 68, 69, 247]); // C, D, E, ÷ ASCII decimal value
     /// let s2 = NormalSet::new(&[65u8, 66, 247]); // A, B, ÷ ASCII decimal value
     /// s.add(s2);
     /// assert_eq!(s.size(), 6); // [65u8, 66, 67, 68, 69, 247]
     /// ```
     pub fn add(&mut self, s2: NormalSet) {
-        self.bits |= s2.bits;
+        *self |= s2;
+    }
+
+    /// Returns a [`NormalSet`] matching the characters of a [`NormalSet`] regardless of their
+    /// case: the other case of every ASCII letter is added.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// let mut s = NormalSet::range(b'a', b'c');
+    /// s.add(NormalSet::new(&[b'Z', b'_', 233])); // 233 is é in Latin-1
+    /// let s2 = s.case_fold();
+    /// assert_eq!(s2.size(), 10);
+    /// assert!(s2.has(b'B') && s2.has(b'z') && !s2.has(201)); // É is not folded
+    /// ```
+    pub const fn case_fold(&self) -> NormalSet {
+        // ASCII letters are bits 1 to 26 (uppercase) and 33 to 58 (lowercase) of word 1.
+        const LETTERS: u64 = 0x07ff_fffe_07ff_fffe;
+        let letters = self.bits[1] & LETTERS;
+        let mut bits = self.bits;
+        bits[1] |= letters.rotate_left(32) & LETTERS;
+        NormalSet { bits }
     }
 
     /// Returns all non-matched characters of a [`NormalSet`]
@@ -186,20 +526,36 @@ impl NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::NormalSet;
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::new(&[67u8, 68, 69, 247]); // C, D, E, ÷ ASCII decimal value
     /// let s2 = s.complement();
     /// assert_eq!(s2.size(), 252); // 256-4
     /// ```
-    pub fn complement(&self) -> NormalSet {
-        let mut s = Self {
-            bits: bitvec![u8, Lsb0; 0; 256],
-        };
-        //s.bits = !self.bits;
-        for i in 0usize..256 {
-            s.bits.set(i, self.bits.get(i).unwrap().not());
+    pub const fn complement(&self) -> NormalSet {
+        let b = self.bits;
+        NormalSet {
+            bits: [!b[0], !b[1], !b[2], !b[3]],
         }
-        s
+    }
+
+    /// Instanciate a [`NormalSet`] with a given charset; unlike [`Set::new`], usable in
+    /// constants
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// const SPACES: NormalSet = NormalSet::from_bytes(b" \t\r\n");
+    /// assert_eq!(SPACES.size(), 4);
+    /// assert!(SPACES.has(b'\t'));
+    /// ```
+    pub const fn from_bytes(chars: &[u8]) -> NormalSet {
+        let mut bits = [0; 4];
+        let mut i = 0;
+        while i < chars.len() {
+            bits[chars[i] as usize / 64] |= 1 << (chars[i] % 64);
+            i += 1;
+        }
+        NormalSet { bits }
     }
 
     /// Returns true if [`NormalSet`] can be converted to a [`SmallSet`]
@@ -208,14 +564,26 @@ impl NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::NormalSet;
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::new(&[67u8, 68, 69, 247]); // C, D, E, ÷ ASCII decimal value
     /// let s2 = NormalSet::new(&[65u8, 127]);
     /// assert_eq!(s.is_small(), false);
     /// assert_eq!(s2.is_small(), true);
     /// ```
-    pub fn is_small(&self) -> bool {
-        self.bits[128..256].count_ones() == 0
+    pub const fn is_small(&self) -> bool {
+        self.bits[2] | self.bits[3] == 0
+    }
+
+    /// Returns an iterator over the characters of a [`NormalSet`], in increasing order
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// let s = NormalSet::new(&[247u8, 67, 68]); // ÷, C, D ASCII decimal value
+    /// assert_eq!(s.iter().collect::<Vec<u8>>(), vec![67, 68, 247]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=255u8).filter(move |c| self.has(*c))
     }
 
     /// Returns a [`NormalSet`] matching all characters between low and high inclusive
@@ -224,7 +592,6 @@ impl NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::NormalSet;
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::range(48, 57); // 0 to 9 in ASCII decimal value
     /// assert_eq!(s.size(), 10);
     /// assert_eq!(s.is_small(), true);
@@ -234,14 +601,25 @@ impl NormalSet {
     /// assert_eq!(s.has(57), true);
     /// assert_eq!(s.has(58), false);
     /// ```
-    pub fn range(low: u8, high: u8) -> NormalSet {
-        let mut s = Self {
-            bits: bitvec![u8, Lsb0; 0; 256],
-        };
-        for i in low..=high {
-            s.bits.set(i as usize, true);
+    pub const fn range(low: u8, high: u8) -> NormalSet {
+        let mut bits = [0; 4];
+        let mut word = 0;
+        while word < 4 {
+            // bits of the word from low to high, inclusive.
+            let first = word * 64;
+            let last = first + 63;
+            if low as usize <= last && high as usize >= first {
+                let from = (low as usize).saturating_sub(first);
+                let to = if (high as usize) < last {
+                    high as usize - first
+                } else {
+                    63
+                };
+                bits[word] = (u64::MAX >> (63 - to)) & (u64::MAX << from);
+            }
+            word += 1;
         }
-        s
+        NormalSet { bits }
     }
 
     /// [NormalSet::smallset()] method converts a [`NormalSet`] into a [`SmallSet`]
@@ -250,7 +628,6 @@ impl NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::{NormalSet, SmallSet};
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::range(120, 130);
     /// let s2 = s.smallset();
     /// assert_eq!(s.size(), 11);
@@ -266,49 +643,64 @@ impl NormalSet {
     /// assert_eq!(s2.has(128), false);
     /// assert_eq!(s2.has(131), false);
     /// ```
-    pub fn smallset(&self) -> SmallSet {
+    pub const fn smallset(&self) -> SmallSet {
         // 0..128
         SmallSet {
-            bits: self.bits[0..128].to_bitvec(),
+            bits: [self.bits[0], self.bits[1]],
         }
     }
 
     /// [NormalSet::string()] returns the string represention of the charset
     ///
+    /// The charset is written in class syntax, which [`NormalSet::from_str`] parses back:
+    /// visible ASCII characters as themselves, `\`, `[`, `]`, `-` and `^` escaped with a
+    /// backslash, other characters as `\xHH`, and 3 or more consecutive characters as a
+    /// `low-high` range. Sets of more than 128 characters are written negated, `[^...]`.
+    /// The output contains no white space.
+    ///
     /// # Examples
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::{NormalSet, SmallSet};
-    /// use bitvec::prelude::*;
-    /// let mut s = NormalSet::range(120, 130);
-    /// let s2 = NormalSet::range(110, 115);
+    /// let mut s = NormalSet::range(b'a', b'z');
+    /// let s2 = NormalSet::range(b'0', b'9');
     /// s.add(s2);
     /// let output = s.string();
-    /// assert_eq!(output, "{110..116,120..131}");
+    /// assert_eq!(output, "[0-9a-z]");
+    /// assert_eq!(NormalSet::new(&[b'a', b'b', b'-', b' ', 255]).string(), r"[\x20\-ab\xff]");
+    /// assert_eq!(NormalSet::new(&[b'\n']).complement().string(), r"[^\x0a]");
+    /// assert_eq!(NormalSet::new(&[]).string(), "[]");
+    /// let parsed: NormalSet = output.parse().unwrap();
+    /// assert_eq!(parsed, s);
     /// ```
     pub fn string(&self) -> String {
-        let mut s = String::new();
-        let mut inrange = false;
-        for b in 0..=255 {
-            if self.has(b) && b == 255 {
-                s += "\u{00ff}"; // C1 command. Unicode internal stuff.
-            } else if self.has(b) && !inrange {
-                inrange = true;
-                if self.has(b + 1) {
-                    s += &b.to_string();
-                    s += "..";
+        // more than half of the characters: the complement is shorter.
+        let (mut string, set) = match self.size() > 128 {
+            true => (String::from("[^"), self.complement()),
+            false => (String::from("["), *self),
+        };
+        let mut c = 0usize;
+        while c < 256 {
+            if !set.has(c as u8) {
+                c += 1;
+                continue;
+            }
+            let low = c;
+            while c < 256 && set.has(c as u8) {
+                c += 1;
+            }
+            string.push_str(&escape(low as u8));
+            match c - low {
+                1 => {}
+                2 => string.push_str(&escape(low as u8 + 1)),
+                _ => {
+                    string.push('-');
+                    string.push_str(&escape((c - 1) as u8));
                 }
-            } else if !self.has(b) && inrange {
-                inrange = false;
-                s += &b.to_string();
-                s += ",";
             }
         }
-        if !s.is_empty() && s.ends_with(',') {
-            s.pop();
-        }
-        s = "{".to_owned() + &s + "}";
-        s
+        string.push(']');
+        string
     }
 
     /// [`NormalSet::sub()`] method substracts a [`NormalSet`] to the existing one (not operation)
@@ -317,7 +709,6 @@ impl NormalSet {
     /// ```
     /// use crate::ripeg::charset::Set;
     /// use crate::ripeg::charset::{NormalSet, SmallSet};
-    /// use bitvec::prelude::*;
     /// let s = NormalSet::range(120, 130); // size 11
     /// let s2 = NormalSet::range(125, 127); // size 3
     /// let s3 = s.sub(s2); // 120..125, 128..131
@@ -341,11 +732,227 @@ impl NormalSet {
     /// assert_eq!(s5.has(120), true);
     /// ```
     pub fn sub(&self, s2: NormalSet) -> NormalSet {
-        let mut s = Self {
-            bits: bitvec![u8, Lsb0; 0; 256],
+        self - s2
+    }
+
+    /// Returns the union of two [`NormalSet`]s, as the `|` operator does; unlike the
+    /// operator, usable in constants
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// const HEX: NormalSet = NormalSet::range(b'0', b'9').union(&NormalSet::range(b'a', b'f'));
+    /// assert_eq!(HEX.size(), 16);
+    /// ```
+    pub const fn union(&self, other: &NormalSet) -> NormalSet {
+        let (a, b) = (self.bits, other.bits);
+        NormalSet {
+            bits: [a[0] | b[0], a[1] | b[1], a[2] | b[2], a[3] | b[3]],
+        }
+    }
+}
+
+/// Predefined classes, the POSIX ones of the C locale plus a few others
+///
+/// They can be used directly with [`crate::pattern::class`], by name in class syntax
+/// (see [`NormalSet::from_str`]) and in grammar texts (see [`crate::re`]), or looked up
+/// with [`NormalSet::named`].
+impl NormalSet {
+    /// `[0-9]`
+    pub const DIGIT: NormalSet = NormalSet::range(b'0', b'9');
+    /// `[0-9a-fA-F]`
+    pub const XDIGIT: NormalSet = NormalSet::DIGIT
+        .union(&NormalSet::range(b'a', b'f'))
+        .union(&NormalSet::range(b'A', b'F'));
+    /// `[a-z]`
+    pub const LOWER: NormalSet = NormalSet::range(b'a', b'z');
+    /// `[A-Z]`
+    pub const UPPER: NormalSet = NormalSet::range(b'A', b'Z');
+    /// `[a-zA-Z]`
+    pub const ALPHA: NormalSet = NormalSet::LOWER.union(&NormalSet::UPPER);
+    /// `[a-zA-Z0-9]`
+    pub const ALNUM: NormalSet = NormalSet::ALPHA.union(&NormalSet::DIGIT);
+    /// `[a-zA-Z0-9_]`
+    pub const WORD: NormalSet = NormalSet::ALNUM.union(&NormalSet::from_bytes(b"_"));
+    /// `[ \t]`
+    pub const BLANK: NormalSet = NormalSet::from_bytes(b" \t");
+    /// `[ \t\n\v\f\r]`
+    pub const SPACE: NormalSet = NormalSet::from_bytes(b" \t\n\x0b\x0c\r");
+    /// `[!-/:-@[-`{-~]`, visible characters which are neither letters nor digits
+    pub const PUNCT: NormalSet = NormalSet::range(b'!', b'/')
+        .union(&NormalSet::range(b':', b'@'))
+        .union(&NormalSet::range(b'[', b'`'))
+        .union(&NormalSet::range(b'{', b'~'));
+    /// `[!-~]`, visible characters
+    pub const GRAPH: NormalSet = NormalSet::range(b'!', b'~');
+    /// `[ -~]`, visible characters and space
+    pub const PRINT: NormalSet = NormalSet::range(b' ', b'~');
+    /// `[\x00-\x1f\x7f]`, control characters
+    pub const CNTRL: NormalSet = NormalSet::range(0, 0x1f).union(&NormalSet::from_bytes(b"\x7f"));
+    /// `[\x00-\x7f]`
+    pub const ASCII: NormalSet = NormalSet::range(0, 0x7f);
+    /// `[\xc2-\xf4]`, first u8 of the multi-u8 sequences of well-formed UTF-8
+    pub const UTF8_LEAD: NormalSet = NormalSet::range(0xc2, 0xf4);
+    /// `[\x80-\xbf]`, u8 following the first one of a UTF-8 sequence
+    pub const UTF8_CONTINUATION: NormalSet = NormalSet::range(0x80, 0xbf);
+
+    /// Returns the predefined class named name, or None if there is no such class.
+    ///
+    /// Names are the POSIX classes `alnum`, `alpha`, `blank`, `cntrl`, `digit`, `graph`,
+    /// `lower`, `print`, `punct`, `space`, `upper` and `xdigit`, plus `ascii`, `word`,
+    /// `utf8_lead` and `utf8_continuation`, each one naming the constant of the same name
+    /// in uppercase.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// assert_eq!(NormalSet::named("xdigit"), Some(NormalSet::XDIGIT));
+    /// assert_eq!(NormalSet::XDIGIT.size(), 22);
+    /// assert_eq!(NormalSet::named("punct").unwrap().string(), r"[!-/:-@\[-`{-~]");
+    /// assert_eq!(NormalSet::named("Digit"), None);
+    /// ```
+    pub fn named(name: &str) -> Option<NormalSet> {
+        Some(match name {
+            "alnum" => NormalSet::ALNUM,
+            "alpha" => NormalSet::ALPHA,
+            "ascii" => NormalSet::ASCII,
+            "blank" => NormalSet::BLANK,
+            "cntrl" => NormalSet::CNTRL,
+            "digit" => NormalSet::DIGIT,
+            "graph" => NormalSet::GRAPH,
+            "lower" => NormalSet::LOWER,
+            "print" => NormalSet::PRINT,
+            "punct" => NormalSet::PUNCT,
+            "space" => NormalSet::SPACE,
+            "upper" => NormalSet::UPPER,
+            "utf8_continuation" => NormalSet::UTF8_CONTINUATION,
+            "utf8_lead" => NormalSet::UTF8_LEAD,
+            "word" => NormalSet::WORD,
+            "xdigit" => NormalSet::XDIGIT,
+            _ => return None,
+        })
+    }
+}
+
+/// Parses a [`NormalSet`] written in class syntax, as [`NormalSet::string`] writes it.
+///
+/// ```text
+/// Class <- '[' '^'? Item* ']'
+/// Item  <- '[:' Name ':]' / Char '-' Char / Char
+/// Char  <- '\' [nrtfv0] / '\x' [0-9a-fA-F]{2} / '\' [!-/:-@[-`{-~] / [\x00-\x7f] except ']'
+/// ```
+///
+/// `[]` is the empty set and `[^]` the full one. A `-` which does not start a range, at
+/// the start or at the end of the class, stands for itself. Names are those of
+/// [`NormalSet::named`], such as the POSIX classes. Non-ASCII characters are written with
+/// `\x` escapes.
+impl FromStr for NormalSet {
+    type Err = Error;
+
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::charset::NormalSet;
+    /// let s: NormalSet = r"[a-zA-Z_\x80-\xff]".parse().unwrap();
+    /// assert_eq!(s.size(), 26 + 26 + 1 + 128);
+    /// assert_eq!(s.string(), r"[^\x00-@\[-\^`{-\x7f]");
+    /// assert_eq!(s.string().parse::<NormalSet>().unwrap(), s);
+    /// let s: NormalSet = r"[^[:digit:][:space:]\-]".parse().unwrap();
+    /// assert_eq!(s.size(), 256 - 10 - 6 - 1);
+    /// let e = "[a-z".parse::<NormalSet>().err().unwrap();
+    /// assert_eq!(e.to_string(), "column 5: unterminated class");
+    /// assert!("[z-a]".parse::<NormalSet>().is_err());
+    /// assert!("[[:klingon:]]".parse::<NormalSet>().is_err());
+    /// ```
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let bytes = text.as_bytes();
+        let error = |pos: usize, message: &str| Error {
+            column: pos + 1,
+            message: message.to_owned(),
         };
-        s.bits = !s2.bits;
-        s.bits &= self.bits.to_bitvec();
-        s
+        if bytes.first() != Some(&b'[') {
+            return Err(error(0, "expected '['"));
+        }
+        let mut pos = 1;
+        let negated = bytes.get(pos) == Some(&b'^');
+        if negated {
+            pos += 1;
+        }
+        let mut set = NormalSet::new(&[]);
+        loop {
+            let start = pos;
+            match bytes.get(pos) {
+                None => return Err(error(pos, "unterminated class")),
+                Some(b']') => break,
+                Some(b'[') if bytes.get(pos + 1) == Some(&b':') => {
+                    let name = &text[pos + 2..];
+                    let end = name
+                        .find(":]")
+                        .ok_or_else(|| error(start, "unterminated class name"))?;
+                    set |= NormalSet::named(&name[..end])
+                        .ok_or_else(|| error(start, "unknown class name"))?;
+                    pos += end + 4;
+                }
+                Some(_) => {
+                    let low = parse_char(bytes, &mut pos).map_err(|m| error(start, m))?;
+                    if bytes.get(pos) == Some(&b'-')
+                        && !matches!(bytes.get(pos + 1), None | Some(b']'))
+                    {
+                        pos += 1;
+                        let high = parse_char(bytes, &mut pos).map_err(|m| error(start, m))?;
+                        if high < low {
+                            return Err(error(start, "invalid class range"));
+                        }
+                        set |= NormalSet::range(low, high);
+                    } else {
+                        set |= NormalSet::from_bytes(&[low]);
+                    }
+                }
+            }
+        }
+        if pos + 1 < bytes.len() {
+            return Err(error(pos + 1, "unexpected text after class"));
+        }
+        Ok(if negated { !set } else { set })
     }
 }
+
+/// Writes u8 c as a character of class syntax, see [`NormalSet::string`].
+fn escape(c: u8) -> String {
+    match c {
+        b'\\' | b'[' | b']' | b'-' | b'^' => format!("\\{}", c as char),
+        b'!'..=b'~' => (c as char).to_string(),
+        _ => format!("\\x{:02x}", c),
+    }
+}
+
+/// Parses the character of class syntax at pos in bytes, moving pos after it.
+fn parse_char(bytes: &[u8], pos: &mut usize) -> Result<u8, &'static str> {
+    let (c, length) = match (bytes.get(*pos), bytes.get(*pos + 1)) {
+        (None, _) => return Err("unterminated class"),
+        (Some(b'\\'), Some(b'x')) => {
+            let hex = bytes.get(*pos + 2..*pos + 4).unwrap_or_default();
+            match std::str::from_utf8(hex).map(|h| u8::from_str_radix(h, 16)) {
+                Ok(Ok(c)) if hex.iter().all(u8::is_ascii_hexdigit) => (c, 4),
+                _ => return Err("invalid hexadecimal escape"),
+            }
+        }
+        (Some(b'\\'), Some(e)) => match e {
+            b'n' => (b'\n', 2),
+            b'r' => (b'\r', 2),
+            b't' => (b'\t', 2),
+            b'f' => (0x0c, 2),
+            b'v' => (0x0b, 2),
+            b'0' => (0, 2),
+            _ if e.is_ascii_punctuation() => (*e, 2),
+            _ => return Err("invalid escape sequence"),
+        },
+        (Some(b'\\'), None) => return Err("unterminated class"),
+        (Some(c), _) if c.is_ascii() => (*c, 1),
+        (Some(_), _) => return Err("non-ASCII character, use a \\x escape"),
+    };
+    *pos += length;
+    Ok(c)
+}
@@ -0,0 +1,469 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! grammar module provides sets of named rules referencing each other, and
+//! their compilation into a single [`Program`].
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::isa::{self, Instr, Program};
+use crate::pattern::{self, Analysis, Pattern, Rules};
+
+/// A set of named rules, each one being a [`Pattern`] which can reference other
+/// rules with [`pattern::non_term`].
+pub struct Grammar {
+    /// Name of the rule matching the subject.
+    start: String,
+    /// Rules, in definition order.
+    rules: Vec<(String, Pattern)>,
+    /// true if rule invocations are memoized.
+    memoize: bool,
+    /// true if left-recursive rules are grown instead of rejected.
+    left_recursion: bool,
+    /// Recovery rule names, keyed by error label.
+    recovery: Vec<(usize, String)>,
+}
+
+/// Errors returned by [`Grammar`] compilation.
+#[derive(Debug)]
+pub enum Error {
+    /// Several rules share the same name.
+    DuplicateRule(String),
+    /// Labels of the compiled [`Program`] cannot be resolved.
+    Label(isa::Error),
+    /// A rule cannot be compiled, or the start rule does not exist.
+    Pattern(pattern::Error),
+    /// Rules call each other without consuming any u8, which would recurse forever.
+    /// The cycle of rule names starts and ends with the same rule.
+    LeftRecursion(Vec<String>),
+    /// A rule repeats a nullable pattern, which would loop forever.
+    NullableLoop(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::DuplicateRule(name) => write!(f, "rule '{}' is defined more than once", name),
+            Error::Label(e) => e.fmt(f),
+            Error::Pattern(e) => e.fmt(f),
+            Error::LeftRecursion(cycle) => write!(f, "left recursion: {}", cycle.join(" -> ")),
+            Error::NullableLoop(name) => write!(
+                f,
+                "rule '{}' repeats a pattern matching the empty string",
+                name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<isa::Error> for Error {
+    fn from(e: isa::Error) -> Self {
+        Error::Label(e)
+    }
+}
+
+impl From<pattern::Error> for Error {
+    fn from(e: pattern::Error) -> Self {
+        Error::Pattern(e)
+    }
+}
+
+/// Methods for a [`Grammar`]
+impl Grammar {
+    /// Instanciate a new [`Grammar`] without rules, start being the name of the rule
+    /// the subject must match.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::grammar::Grammar;
+    /// let g = Grammar::new("Expr");
+    /// assert_eq!(g.size(), 0);
+    /// ```
+    pub fn new(start: &str) -> Self {
+        Self {
+            start: start.to_owned(),
+            rules: Vec::new(),
+            memoize: false,
+            left_recursion: false,
+            recovery: Vec::new(),
+        }
+    }
+
+    /// Enables or disables the memoization of rule invocations (packrat parsing).
+    ///
+    /// When enabled, each compiled rule is enclosed by [`Instr::MemoOpen`] and
+    /// [`Instr::MemoClose`], the memoization ID being the rule index. Disabled by default.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::grammar::Grammar;
+    /// use crate::ripeg::memo::Table;
+    /// use crate::ripeg::pattern::literal;
+    /// use crate::ripeg::vm::VM;
+    /// let mut g = Grammar::new("A");
+    /// g.add("A", literal("a"));
+    /// g.memoize(true);
+    /// let p = g.compile().unwrap();
+    /// let mut table = Table::new();
+    /// assert!(VM::new(&p).exec_memo(b"a", &mut table).matched);
+    /// assert_eq!(table.len(), 1);
+    /// ```
+    pub fn memoize(&mut self, enable: bool) {
+        self.memoize = enable;
+    }
+
+    /// Enables or disables the support of left-recursive rules.
+    ///
+    /// When enabled, rules calling themselves before consuming any u8, directly or not, are
+    /// called with [`Instr::LeftCall`] instead of being rejected by [`Grammar::compile`]:
+    /// their match is grown from seeds (Warth et al.), which makes them match as
+    /// left-associative repetitions. Left-recursive rules are not memoized, and their
+    /// references can carry a precedence, see [`pattern::non_term_prec`]. Disabled by
+    /// default.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::grammar::Grammar;
+    /// use crate::ripeg::pattern::{capture, class, concat, literal, non_term, or, plus};
+    /// use crate::ripeg::vm::VM;
+    /// // Expr <- {Expr '-' Term} / Term
+    /// // Term <- [0-9]+
+    /// let mut g = Grammar::new("Expr");
+    /// g.add("Expr", or(vec![
+    ///     capture(1, concat(vec![non_term("Expr"), literal("-"), non_term("Term")])),
+    ///     non_term("Term"),
+    /// ]));
+    /// g.add("Term", plus(class(NormalSet::range(b'0', b'9'))));
+    /// assert!(g.compile().is_err());
+    /// g.left_recursion(true);
+    /// let p = g.compile().unwrap();
+    /// let m = VM::new(&p).exec(b"7-2-1");
+    /// assert_eq!(m.length, 5);
+    /// // (7-2)-1
+    /// assert_eq!((m.captures[0].start, m.captures[0].end), (0, 5));
+    /// assert_eq!((m.captures[0].children[0].start, m.captures[0].children[0].end), (0, 3));
+    /// ```
+    pub fn left_recursion(&mut self, enable: bool) {
+        self.left_recursion = enable;
+    }
+
+    /// Adds a rule named name to a [`Grammar`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::grammar::Grammar;
+    /// use crate::ripeg::pattern::literal;
+    /// let mut g = Grammar::new("A");
+    /// g.add("A", literal("a"));
+    /// assert_eq!(g.size(), 1);
+    /// ```
+    pub fn add(&mut self, name: &str, pattern: Pattern) {
+        self.rules.push((name.to_owned(), pattern));
+    }
+
+    /// Attaches the rule named rule to error label label: when [`pattern::throw`] throws
+    /// the label, the rule is matched instead, and the subject is rejected only if it fails.
+    ///
+    /// This allows to report several errors in a subject, each one being recorded in
+    /// [`crate::vm::Match::errors`], the recovery rule skipping the erroneous part of the
+    /// subject to resume matching.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::re::parse;
+    /// use crate::ripeg::vm::VM;
+    /// // Each entry must end with ';', otherwise label 1 is thrown and the recovery
+    /// // rule skips up to the next line.
+    /// let mut g = parse("
+    ///     Entries <- (Entry '\\n')*
+    ///     Entry   <- [a-z]+ '=' [0-9]+ (';' / %{1})
+    ///     Skip    <- (!'\\n' .)*
+    /// ").unwrap();
+    /// g.recover(1, "Skip");
+    /// let p = g.compile().unwrap();
+    /// let m = VM::new(&p).exec(b"a=1;\nb=2 oops\nc=3;\nd=4\n");
+    /// assert!(m.matched);
+    /// assert_eq!(m.length, 23);
+    /// let errors: Vec<usize> = m.errors.iter().map(|e| e.offset).collect();
+    /// assert_eq!(errors, vec![8, 22]);
+    /// ```
+    pub fn recover(&mut self, label: usize, rule: &str) {
+        self.recovery.push((label, rule.to_owned()));
+    }
+
+    /// Number of rules of a [`Grammar`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::grammar::Grammar;
+    /// use crate::ripeg::pattern::{literal, non_term};
+    /// let mut g = Grammar::new("A");
+    /// g.add("A", non_term("B"));
+    /// g.add("B", literal("b"));
+    /// assert_eq!(g.size(), 2);
+    /// ```
+    pub fn size(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Computes the static properties of each rule of a [`Grammar`], by name, see [`Analysis`].
+    ///
+    /// Properties of recursive rules are refined until they are stable. When several
+    /// rules share the same name, the first one is analyzed.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::Set;
+    /// use crate::ripeg::re::parse;
+    /// let g = parse("
+    ///     List  <- Item (',' Item)*
+    ///     Item  <- [0-9]+ / '(' List? ')'
+    ///     Space <- ' '*
+    /// ").unwrap();
+    /// let a = g.analyze();
+    /// assert_eq!(a["List"].first.size(), 11);
+    /// assert!(a["List"].first.has(b'('));
+    /// assert!(!a["List"].nullable);
+    /// assert!(a["Space"].nullable && a["Space"].no_fail);
+    /// assert!(!a["Item"].head_fail);
+    /// ```
+    pub fn analyze(&self) -> HashMap<String, Analysis> {
+        let mut analysis = HashMap::new();
+        for (name, _) in &self.rules {
+            analysis.insert(name.clone(), Analysis::none());
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, (name, p)) in self.rules.iter().enumerate() {
+                if self.rules[..index].iter().any(|(n, _)| n == name) {
+                    continue;
+                }
+                let a = p.analyze_with(&analysis);
+                if !a.same(&analysis[name]) {
+                    analysis.insert(name.clone(), a);
+                    changed = true;
+                }
+            }
+        }
+        analysis
+    }
+
+    /// Returns the rules each rule can call before consuming any u8, analysis being the
+    /// [`Analysis`] of each rule.
+    fn left_calls(&self, analysis: &HashMap<String, Analysis>) -> HashMap<&str, Vec<String>> {
+        let mut calls = HashMap::new();
+        for (name, p) in &self.rules {
+            let mut left = Vec::new();
+            p.left_calls(analysis, &mut left);
+            calls.entry(name.as_str()).or_insert(left);
+        }
+        calls
+    }
+
+    /// Returns the first cycle of rules calling each other without consuming any u8,
+    /// analysis being the [`Analysis`] of each rule.
+    fn left_cycle(&self, analysis: &HashMap<String, Analysis>) -> Option<Vec<String>> {
+        let calls = self.left_calls(analysis);
+        let mut done = HashMap::new();
+        let mut path = Vec::new();
+        self.rules
+            .iter()
+            .find_map(|(name, _)| left_cycle(name, &calls, &mut done, &mut path))
+    }
+
+    /// Returns the names of the rules which can call themselves before consuming any u8,
+    /// analysis being the [`Analysis`] of each rule.
+    fn left_recursive(&self, analysis: &HashMap<String, Analysis>) -> HashSet<String> {
+        let calls = self.left_calls(analysis);
+        let mut left = HashSet::new();
+        for name in calls.keys() {
+            let mut reached = HashSet::new();
+            let mut pending: Vec<&str> = vec![name];
+            while let Some(rule) = pending.pop() {
+                for callee in calls.get(rule).into_iter().flatten() {
+                    if reached.insert(callee.as_str()) {
+                        pending.push(callee);
+                    }
+                }
+            }
+            if reached.contains(name) {
+                left.insert(name.to_string());
+            }
+        }
+        left
+    }
+
+    /// Compiles a [`Grammar`] into a [`Program`].
+    ///
+    /// Each rule is compiled after an [`Instr::Label`] whose ID is the rule index, and
+    /// ends with [`Instr::Return`]. Non-terminals become [`Instr::Call`] to the rule
+    /// [`Instr::Label`], resolved with [`Program::resolve_labels`]. The [`Program`] calls
+    /// the start rule then ends with [`Instr::End`]. Rule names are kept in the
+    /// [`Program`], see [`Program::rule_name`]. The [`Program`] is finally optimized with
+    /// [`Program::optimize`]. Choices are compiled using the [`Analysis`] of rules,
+    /// see [`Grammar::analyze`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::grammar::Grammar;
+    /// use crate::ripeg::pattern::{class, concat, literal, non_term, or, plus, star};
+    /// use crate::ripeg::vm::VM;
+    /// // Expr <- Term ('+' Term)*
+    /// // Term <- [0-9]+ / '(' Expr ')'
+    /// let mut g = Grammar::new("Expr");
+    /// g.add("Expr", concat(vec![non_term("Term"), star(concat(vec![literal("+"), non_term("Term")]))]));
+    /// g.add("Term", or(vec![
+    ///     plus(class(NormalSet::range(b'0', b'9'))),
+    ///     concat(vec![literal("("), non_term("Expr"), literal(")")]),
+    /// ]));
+    /// let p = g.compile().unwrap();
+    /// let mut vm = VM::new(&p);
+    /// assert_eq!(vm.exec(b"1+(2+30)+4").length, 10);
+    /// assert_eq!(vm.exec(b"1+(2+30").length, 1);
+    /// assert!(!vm.exec(b"+1").matched);
+    /// ```
+    ///
+    /// Errors:
+    /// ```
+    /// use crate::ripeg::grammar::{Error, Grammar};
+    /// use crate::ripeg::pattern::{literal, non_term};
+    /// let mut g = Grammar::new("A");
+    /// g.add("A", non_term("B"));
+    /// assert!(matches!(g.compile(), Err(Error::Pattern(_))));
+    /// g.add("B", literal("b"));
+    /// assert!(g.compile().is_ok());
+    /// g.add("B", literal("c"));
+    /// assert!(matches!(g.compile(), Err(Error::DuplicateRule(_))));
+    /// ```
+    ///
+    /// Left recursion, direct or through nullable patterns, unless enabled with
+    /// [`Grammar::left_recursion`], and repetitions of nullable patterns are rejected:
+    /// ```
+    /// use crate::ripeg::grammar::Error;
+    /// use crate::ripeg::re::parse;
+    /// let g = parse("
+    ///     Expr <- Sum / [0-9]
+    ///     Sum  <- ' '* Expr '+' [0-9]
+    /// ").unwrap();
+    /// match g.compile() {
+    ///     Err(e @ Error::LeftRecursion(_)) => {
+    ///         assert_eq!(e.to_string(), "left recursion: Expr -> Sum -> Expr")
+    ///     }
+    ///     _ => panic!("left recursion not detected"),
+    /// }
+    /// let g = parse("A <- ('a' / '')*").unwrap();
+    /// assert!(matches!(g.compile(), Err(Error::NullableLoop(name)) if name == "A"));
+    /// ```
+    pub fn compile(&self) -> Result<Program, Error> {
+        let mut ids = HashMap::new();
+        for (id, (name, _)) in self.rules.iter().enumerate() {
+            if ids.insert(name.clone(), id).is_some() {
+                return Err(Error::DuplicateRule(name.clone()));
+            }
+        }
+        let analysis = self.analyze();
+        let left = match self.left_recursion {
+            true => self.left_recursive(&analysis),
+            false => HashSet::new(),
+        };
+        let rules = Rules {
+            ids,
+            analysis,
+            left,
+        };
+        let mut code = Vec::new();
+        pattern::non_term(&self.start).emit(&mut code, &rules)?;
+        code.push(Instr::Jump(0));
+        for (id, (name, p)) in self.rules.iter().enumerate() {
+            code.push(Instr::Label(id));
+            if self.memoize && !rules.left.contains(name) {
+                let open = code.len();
+                code.push(Instr::MemoOpen(0, id));
+                p.emit(&mut code, &rules)?;
+                code.push(Instr::MemoClose);
+                code[open] = Instr::MemoOpen(code.len(), id);
+            } else {
+                p.emit(&mut code, &rules)?;
+            }
+            code.push(Instr::Return);
+        }
+        code[1] = Instr::Jump(code.len());
+        code.push(Instr::End);
+        for (name, p) in &self.rules {
+            if p.nullable_loop(&rules.analysis) {
+                return Err(Error::NullableLoop(name.clone()));
+            }
+        }
+        if !self.left_recursion {
+            if let Some(cycle) = self.left_cycle(&rules.analysis) {
+                return Err(Error::LeftRecursion(cycle));
+            }
+        }
+        let mut program = Program::from(code);
+        for (id, (name, _)) in self.rules.iter().enumerate() {
+            program.name_rule(id, name);
+        }
+        for (label, rule) in &self.recovery {
+            match rules.ids.get(rule) {
+                Some(id) => program.recover(*label, *id),
+                None => return Err(pattern::Error::UndefinedNonTerminal(rule.clone()).into()),
+            }
+        }
+        program.resolve_labels()?;
+        program.optimize();
+        Ok(program)
+    }
+}
+
+/// Visits the rules called by rule name before consuming any u8, depth first, and
+/// returns the first cycle found. path holds the rules being visited, done tells
+/// whether a rule was visited already (true) or is being visited (false).
+fn left_cycle<'a>(
+    name: &'a str,
+    calls: &'a HashMap<&str, Vec<String>>,
+    done: &mut HashMap<&'a str, bool>,
+    path: &mut Vec<&'a str>,
+) -> Option<Vec<String>> {
+    match done.get(name) {
+        Some(true) => return None,
+        Some(false) => {
+            let start = path.iter().position(|n| *n == name)?;
+            let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+            cycle.push(name.to_owned());
+            return Some(cycle);
+        }
+        None => {}
+    }
+    // undefined rules are reported by compilation.
+    let callees = calls.get(name)?;
+    done.insert(name, false);
+    path.push(name);
+    for callee in callees {
+        if let Some(cycle) = left_cycle(callee, calls, done, path) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.insert(name, true);
+    None
+}
@@ -0,0 +1,441 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! input module provides the [`ReaderAt`] trait through which [`crate::vm::VM`]
+//! reads its subject, and its implementations for common subject types.
+
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+/// Random access to the u8 of a subject.
+///
+/// # Examples
+/// ```
+/// use std::io;
+/// use crate::ripeg::input::ReaderAt;
+/// use crate::ripeg::memo::Table;
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// // a subject counting its reads.
+/// struct Counted<'a>(&'a [u8], usize);
+/// impl ReaderAt for Counted<'_> {
+///     fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize> {
+///         self.1 += 1;
+///         self.0.read_at(buf, offset)
+///     }
+/// }
+/// let p = parse("S <- ('a' !. / 'a' / 'b')*").unwrap().compile().unwrap();
+/// let mut subject = Counted(b"abababab", 0);
+/// assert_eq!(VM::new(&p).exec_input(&mut subject, &mut Table::new()).unwrap().length, 8);
+/// // the end of the subject is read once, however often the grammar looks past it.
+/// assert_eq!(subject.1, 2);
+/// ```
+pub trait ReaderAt {
+    /// Reads u8 of the subject starting at offset into buf, and returns how many were
+    /// read. Less than `buf.len()` u8 can be read even if the subject is not over,
+    /// 0 means offset is at or past the end of the subject.
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize>;
+
+    /// Returns the whole subject if it is contiguous in memory, so that it is read in
+    /// place rather than copied through [`ReaderAt::read_at`]. None by default.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::{Chunks, ReaderAt};
+    /// let s: &[u8] = b"hello";
+    /// assert_eq!(s.as_slice(), Some(&b"hello"[..]));
+    /// assert_eq!(Chunks::new(vec!["hel", "lo"]).as_slice(), None);
+    /// ```
+    fn as_slice(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+/// [`ReaderAt`] for a slice of u8
+impl ReaderAt for &[u8] {
+    /// Copies the u8 of the slice starting at offset.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let mut s: &[u8] = b"hello";
+    /// let mut buf = [0u8; 4];
+    /// assert_eq!(s.read_at(&mut buf, 2).unwrap(), 3);
+    /// assert_eq!(&buf[..3], b"llo");
+    /// assert_eq!(s.read_at(&mut buf, 5).unwrap(), 0);
+    /// ```
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize> {
+        let rest = self.get(offset..).unwrap_or_default();
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        Ok(n)
+    }
+
+    /// Returns the slice itself.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let s: &[u8] = b"hello";
+    /// assert_eq!(s.as_slice(), Some(&b"hello"[..]));
+    /// ```
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self)
+    }
+}
+
+/// [`ReaderAt`] for a string slice, read as UTF-8 u8
+impl ReaderAt for &str {
+    /// Copies the u8 of the string starting at offset.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let mut s = "hé";
+    /// let mut buf = [0u8; 4];
+    /// assert_eq!(s.read_at(&mut buf, 0).unwrap(), 3);
+    /// assert_eq!(&buf[..3], "hé".as_bytes());
+    /// ```
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize> {
+        self.as_bytes().read_at(buf, offset)
+    }
+
+    /// Returns the u8 of the string.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let s = "hé";
+    /// assert_eq!(s.as_slice(), Some("hé".as_bytes()));
+    /// ```
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self.as_bytes())
+    }
+}
+
+/// [`ReaderAt`] for a vector of u8
+impl ReaderAt for Vec<u8> {
+    /// Copies the u8 of the vector starting at offset.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let mut v = b"hello".to_vec();
+    /// let mut buf = [0u8; 2];
+    /// assert_eq!(v.read_at(&mut buf, 1).unwrap(), 2);
+    /// assert_eq!(&buf, b"el");
+    /// ```
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize> {
+        self.as_slice().read_at(buf, offset)
+    }
+
+    /// Returns the vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let v = b"hello".to_vec();
+    /// assert_eq!(ReaderAt::as_slice(&v), Some(&b"hello"[..]));
+    /// ```
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(Vec::as_slice(self))
+    }
+}
+
+/// [`ReaderAt`] for a String, read as UTF-8 u8
+impl ReaderAt for String {
+    /// Copies the u8 of the String starting at offset.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let mut s = String::from("hello");
+    /// let mut buf = [0u8; 8];
+    /// assert_eq!(s.read_at(&mut buf, 3).unwrap(), 2);
+    /// assert_eq!(&buf[..2], b"lo");
+    /// ```
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize> {
+        self.as_bytes().read_at(buf, offset)
+    }
+
+    /// Returns the u8 of the String.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::ReaderAt;
+    /// let s = String::from("hello");
+    /// assert_eq!(s.as_slice(), Some(&b"hello"[..]));
+    /// ```
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self.as_bytes())
+    }
+}
+
+/// A subject made of several chunks of u8 read one after the other, such as the
+/// chunks of a rope. Chunks are borrowed or owned, nothing is copied at creation.
+pub struct Chunks<C> {
+    /// Chunks of the subject.
+    chunks: Vec<C>,
+    /// Position following the last u8 of each chunk in the subject.
+    ends: Vec<usize>,
+}
+
+/// Methods for [`Chunks`]
+impl<C: AsRef<[u8]>> Chunks<C> {
+    /// Instanciate [`Chunks`] from an iterator over the chunks of the subject.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::Chunks;
+    /// let c = Chunks::new(vec!["hel", "", "lo"]);
+    /// assert_eq!(c.len(), 5);
+    /// ```
+    pub fn new<I: IntoIterator<Item = C>>(chunks: I) -> Self {
+        let chunks: Vec<C> = chunks.into_iter().collect();
+        let mut end = 0;
+        let ends = chunks
+            .iter()
+            .map(|c| {
+                end += c.as_ref().len();
+                end
+            })
+            .collect();
+        Self { chunks, ends }
+    }
+
+    /// Number of u8 of the subject.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::Chunks;
+    /// let c = Chunks::new(vec![b"ab".to_vec(), b"cde".to_vec()]);
+    /// assert_eq!(c.len(), 5);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.ends.last().copied().unwrap_or(0)
+    }
+
+    /// Returns true if the subject holds no u8.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::Chunks;
+    /// assert!(Chunks::new(vec!["", ""]).is_empty());
+    /// assert!(!Chunks::new(vec!["", "a"]).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// [`ReaderAt`] for [`Chunks`]
+impl<C: AsRef<[u8]>> ReaderAt for Chunks<C> {
+    /// Copies the u8 of the subject starting at offset, across chunks.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::input::{Chunks, ReaderAt};
+    /// let mut c = Chunks::new(vec!["hel", "", "lo", " world"]);
+    /// let mut buf = [0u8; 6];
+    /// assert_eq!(c.read_at(&mut buf, 2).unwrap(), 6);
+    /// assert_eq!(&buf, b"llo wo");
+    /// assert_eq!(c.read_at(&mut buf, 11).unwrap(), 0);
+    /// ```
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize> {
+        // first chunk ending after offset.
+        let mut i = self.ends.partition_point(|end| *end <= offset);
+        let mut n = 0;
+        while n < buf.len() && i < self.chunks.len() {
+            let chunk = self.chunks[i].as_ref();
+            let start = self.ends[i] - chunk.len();
+            n += (&chunk[offset + n - start..]).read_at(&mut buf[n..], 0)?;
+            i += 1;
+        }
+        Ok(n)
+    }
+}
+
+/// A subject read from a [`Read`] + [`Seek`] source, such as a [`std::fs::File`].
+pub struct Seekable<R> {
+    /// Source of the subject.
+    inner: R,
+}
+
+/// Methods for [`Seekable`]
+impl<R: Read + Seek> Seekable<R> {
+    /// Instanciate a [`Seekable`] reading its subject from inner.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Cursor;
+    /// use crate::ripeg::input::Seekable;
+    /// let s = Seekable::new(Cursor::new(b"hello"));
+    /// assert_eq!(s.into_inner().into_inner(), b"hello");
+    /// ```
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the source of a [`Seekable`].
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Cursor;
+    /// use crate::ripeg::input::Seekable;
+    /// let s = Seekable::new(Cursor::new(vec![1u8, 2]));
+    /// assert_eq!(s.into_inner().into_inner(), vec![1u8, 2]);
+    /// ```
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// [`ReaderAt`] for [`Seekable`]
+impl<R: Read + Seek> ReaderAt for Seekable<R> {
+    /// Seeks the source to offset and reads it, reading again when interrupted.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom};
+    /// use crate::ripeg::input::{ReaderAt, Seekable};
+    /// let mut s = Seekable::new(Cursor::new(b"hello world"));
+    /// let mut buf = [0u8; 5];
+    /// assert_eq!(s.read_at(&mut buf, 6).unwrap(), 5);
+    /// assert_eq!(&buf, b"world");
+    ///
+    /// // a source interrupted by a signal on every other read.
+    /// struct Flaky(Cursor<Vec<u8>>, bool);
+    /// impl Read for Flaky {
+    ///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///         self.1 = !self.1;
+    ///         match self.1 {
+    ///             true => Err(ErrorKind::Interrupted.into()),
+    ///             false => self.0.read(buf),
+    ///         }
+    ///     }
+    /// }
+    /// impl Seek for Flaky {
+    ///     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+    ///         self.0.seek(pos)
+    ///     }
+    /// }
+    /// let mut s = Seekable::new(Flaky(Cursor::new(b"hello".to_vec()), false));
+    /// assert_eq!(s.read_at(&mut buf, 1).unwrap(), 4);
+    /// assert_eq!(&buf[..4], b"ello");
+    /// ```
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> io::Result<usize> {
+        self.inner.seek(SeekFrom::Start(offset as u64))?;
+        loop {
+            match self.inner.read(buf) {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                r => return r,
+            }
+        }
+    }
+}
+
+/// Size of the window [`Reader`] keeps in memory.
+const WINDOW: usize = 4096;
+
+/// Buffered access to the u8 of a [`ReaderAt`], one at a time. Subjects contiguous in
+/// memory (see [`ReaderAt::as_slice`]) are read in place.
+pub(crate) struct Reader<'r, R: ReaderAt + ?Sized> {
+    /// Subject.
+    input: &'r mut R,
+    /// Window over the subject, allocated on the first read through it.
+    buf: Vec<u8>,
+    /// Position of the first u8 of the window in the subject.
+    start: usize,
+    /// Number of u8 of the subject held by the window.
+    len: usize,
+    /// Length of the subject, once its end has been read.
+    end: Option<usize>,
+}
+
+impl<'r, R: ReaderAt + ?Sized> Reader<'r, R> {
+    /// Instanciate a [`Reader`] over input.
+    pub(crate) fn new(input: &'r mut R) -> Self {
+        Self {
+            input,
+            buf: Vec::new(),
+            start: 0,
+            len: 0,
+            end: None,
+        }
+    }
+
+    /// Returns the u8 at position pos of the subject, None if pos is past its end.
+    #[inline]
+    pub(crate) fn get(&mut self, pos: usize) -> io::Result<Option<u8>> {
+        if let Some(subject) = self.input.as_slice() {
+            return Ok(subject.get(pos).copied());
+        }
+        if pos.wrapping_sub(self.start) < self.len {
+            return Ok(Some(self.buf[pos - self.start]));
+        }
+        if self.past_end(pos) {
+            return Ok(None);
+        }
+        self.load(pos)?;
+        Ok(self.buf[..self.len].first().copied())
+    }
+
+    /// Returns the u8 of the subject from position pos, empty if pos is past its end:
+    /// the rest of the subject if it is contiguous, the rest of the window otherwise.
+    #[inline]
+    pub(crate) fn window(&mut self, pos: usize) -> io::Result<&[u8]> {
+        if self.input.as_slice().is_none() && pos.wrapping_sub(self.start) >= self.len {
+            if self.past_end(pos) {
+                return Ok(&[]);
+            }
+            self.load(pos)?;
+        }
+        Ok(match self.input.as_slice() {
+            Some(subject) => subject.get(pos..).unwrap_or_default(),
+            None => &self.buf[pos - self.start..self.len],
+        })
+    }
+
+    /// Returns true if the end of the subject has been read, and pos is at or past it.
+    #[inline]
+    fn past_end(&self, pos: usize) -> bool {
+        self.end.map(|end| pos >= end).unwrap_or(false)
+    }
+
+    /// Fills the window from position pos of the subject, recording its end if read.
+    #[cold]
+    fn load(&mut self, pos: usize) -> io::Result<()> {
+        if self.buf.is_empty() {
+            self.buf = vec![0; WINDOW];
+        }
+        self.start = pos;
+        self.len = 0;
+        loop {
+            let n = self
+                .input
+                .read_at(&mut self.buf[self.len..], pos + self.len)?;
+            self.len += n;
+            if n == 0 {
+                self.end = Some(pos + self.len);
+                break;
+            }
+            if self.len == WINDOW {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -18,10 +18,24 @@
 //! isa provides types for all instructions of ripeg VM. aka
 //! Instruction Set Architecture.
 
-use crate::charset::NormalSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::charset::{NormalSet, Set};
+
+/// First bytes of an encoded [`Program`], see [`Program::encode`].
+pub const MAGIC: [u8; 4] = *b"RPEG";
+
+/// Version of the [`Program`] encoding produced by [`Program::encode`].
+///
+/// Version 2 added [`Instr::LeftCall`]. [`Program::decode`] also reads version 1.
+pub const VERSION: u16 = 2;
 
 /// Instruction Pointer
 /// Can get either next instruction address to be executed or None (failure state)
+#[derive(Debug)]
 pub enum IP {
     /// Fail state
     None,
@@ -30,6 +44,7 @@ pub enum IP {
 }
 
 /// List of Instructions
+#[derive(Clone, Copy, Debug)]
 pub enum Instr {
     /// Advances ip and consume n u8 from subject if possible, fails otherwise.
     /// Fails only by reaching the end of the subject.
@@ -39,6 +54,10 @@ pub enum Instr {
     /// Pushes the next [`IP`] to the stack as a return address and jumps to usize.
     /// Used to implement non-terminals.
     Call(usize),
+    /// Opens a capture with ID usize at sp. See [`crate::capture::Node`].
+    CaptureBegin(usize),
+    /// Closes at sp the innermost capture opened by [`Instr::CaptureBegin`].
+    CaptureEnd,
     /// Increment [`IP`] and Consume u8 if it matches, ip becomes None otherwise.
     Char(u8),
     /// Pushes a backtrack entry storing u8 and sp so that parser can backtrack
@@ -58,9 +77,21 @@ pub enum Instr {
     /// Sets ip Index to usize.
     Jump(usize),
     /// used to mark a location in the instruction code with an unique ID.
-    /// Maybe we could get rid of that ?
-    /// Likely to be deleted as Label will probably become a HashMap<Label, Program[index]
+    /// Does nothing when executed. See [`Program::resolve_labels`].
     Label(usize),
+    /// Calls the left-recursive rule at 1st arg with precedence 2nd arg, growing its match
+    /// with seeds (Warth et al.): the rule first fails when called again at the same
+    /// position, then is matched again as long as its match grows, a call at the same
+    /// position returning the previous match. Such a call with a lower precedence fails,
+    /// with a greater precedence it grows a match of its own.
+    LeftCall(usize, usize),
+    /// Pops the memoization entry pushed by [`Instr::MemoOpen`] and stores the
+    /// outcome of the memoized pattern in the [`crate::memo::Table`].
+    MemoClose,
+    /// Looks up the [`crate::memo::Table`] for an entry of memoization ID 2nd arg at sp.
+    /// If the entry is a match, advances sp by its length and jumps to 1st arg, if it is
+    /// a failure, sets ip to None. Otherwise pushes a memoization entry and advances ip.
+    MemoOpen(usize, usize),
     /// No operation
     /// Likely to be deleted ?
     Nop,
@@ -71,7 +102,8 @@ pub enum Instr {
     /// advances ip and consumes u8 from subject if contained in character set [`NormalSet`].
     /// Goes to [`Instr::Fail`] state otherwise.
     Set(NormalSet),
-    /// [`Instr::Span`] equals to [`NormalSet`]*
+    /// [`Instr::Span`] equals to [`NormalSet`]*, the subject being scanned with a
+    /// [`crate::scan::Scanner`].
     Span(NormalSet),
     /// checks if there is at least 1st arg characters remaining -> pushes backtrack
     /// entry and advances sp by 1st arg. if not jumps to 2nd arg.
@@ -86,11 +118,116 @@ pub enum Instr {
     TestSet(NormalSet, usize),
     /// Checks if NormalSet matches at sp -> advances sp. If not jumps to 2nd arg.
     TestSetNoChoice(NormalSet, usize),
+    /// Throws the error label usize at sp: calls the recovery rule of the label like
+    /// [`Instr::Call`] if any (see [`Program::recover`]), rejects the subject otherwise,
+    /// whatever the backtrack entries on the stack.
+    Throw(usize),
+}
+
+/// Errors returned by [`Program`] methods.
+#[derive(Debug)]
+pub enum Error {
+    /// Several [`Instr::Label`] share the same ID.
+    DuplicateLabel(usize),
+    /// An instruction references an ID no [`Instr::Label`] holds.
+    UndefinedLabel(usize),
+    /// Decoded bytes do not start with [`MAGIC`].
+    BadMagic,
+    /// Decoded bytes have been encoded with an unknown version of the encoding.
+    UnsupportedVersion(u16),
+    /// Decoded bytes end in the middle of a [`Program`].
+    Truncated,
+    /// Decoded bytes hold an unknown instruction opcode at the given offset.
+    InvalidOpcode(usize),
+    /// Decoded bytes hold an invalid value at the given offset.
+    Corrupted(usize),
+    /// A listing cannot be parsed: line number, starting at 1, and description.
+    Listing(usize, String),
+    /// The [`Instr`] at the given index cannot be executed safely, see [`Program::verify`].
+    Invalid(usize, String),
+    /// No [`Instr::End`] can be reached from the first [`Instr`].
+    NoEnd,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::DuplicateLabel(id) => write!(f, "label {} is defined more than once", id),
+            Error::UndefinedLabel(id) => write!(f, "label {} is not defined", id),
+            Error::BadMagic => write!(f, "not an encoded program"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported encoding version {}", v),
+            Error::Truncated => write!(f, "encoded program is truncated"),
+            Error::InvalidOpcode(at) => write!(f, "invalid opcode at offset {}", at),
+            Error::Corrupted(at) => write!(f, "invalid value at offset {}", at),
+            Error::Listing(line, message) => write!(f, "line {}: {}", line, message),
+            Error::Invalid(index, message) => write!(f, "instruction {}: {}", index, message),
+            Error::NoEnd => write!(f, "no End instruction is reachable"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Formats an [`Instr`] as its name followed by its arguments, u8 being written
+/// between quotes and [`NormalSet`] with [`NormalSet::string`].
+impl fmt::Display for Instr {
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::isa::Instr;
+    /// assert_eq!(Instr::TestChar(b'a', 7).to_string(), "TestChar 'a' 7");
+    /// assert_eq!(Instr::Char(b'\n').to_string(), "Char '\\x0a'");
+    /// assert_eq!(Instr::Span(NormalSet::range(b'0', b'9')).to_string(), "Span [0-9]");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instr::Any(n) => write!(f, "Any {}", n),
+            Instr::BackCommit(l) => write!(f, "BackCommit {}", l),
+            Instr::Call(l) => write!(f, "Call {}", l),
+            Instr::CaptureBegin(id) => write!(f, "CaptureBegin {}", id),
+            Instr::CaptureEnd => write!(f, "CaptureEnd"),
+            Instr::Char(c) => write!(f, "Char {}", quote(*c)),
+            Instr::Choice(l) => write!(f, "Choice {}", l),
+            Instr::Commit(l) => write!(f, "Commit {}", l),
+            Instr::End => write!(f, "End"),
+            Instr::EndFail => write!(f, "EndFail"),
+            Instr::Fail => write!(f, "Fail"),
+            Instr::FailTwice => write!(f, "FailTwice"),
+            Instr::Jump(l) => write!(f, "Jump {}", l),
+            Instr::Label(id) => write!(f, "Label {}", id),
+            Instr::LeftCall(l, k) => write!(f, "LeftCall {} {}", l, k),
+            Instr::MemoClose => write!(f, "MemoClose"),
+            Instr::MemoOpen(l, id) => write!(f, "MemoOpen {} {}", l, id),
+            Instr::Nop => write!(f, "Nop"),
+            Instr::PartialCommit(l) => write!(f, "PartialCommit {}", l),
+            Instr::Return => write!(f, "Return"),
+            Instr::Set(s) => write!(f, "Set {}", s.string()),
+            Instr::Span(s) => write!(f, "Span {}", s.string()),
+            Instr::TestAny(n, l) => write!(f, "TestAny {} {}", n, l),
+            Instr::TestChar(c, l) => write!(f, "TestChar {} {}", quote(*c), l),
+            Instr::TestCharNoChoice(c, l) => write!(f, "TestCharNoChoice {} {}", quote(*c), l),
+            Instr::TestSet(s, l) => write!(f, "TestSet {} {}", s.string(), l),
+            Instr::TestSetNoChoice(s, l) => write!(f, "TestSetNoChoice {} {}", s.string(), l),
+            Instr::Throw(label) => write!(f, "Throw {}", label),
+        }
+    }
 }
 
 /// A Program is a Vector of Instructions.
 /// struct used here because type does not allow to use impl.
-pub struct Program(Vec<Instr>);
+///
+/// Instructions, rule names and recovery rules are borrowed from static arrays by
+/// [`Program::from_static`], so that a [`Program`] can be a constant, and copied when
+/// modified.
+#[derive(Debug)]
+pub struct Program {
+    /// Instructions.
+    code: Cow<'static, [Instr]>,
+    /// Names of the rules, keyed by the ID of their [`Instr::Label`], sorted by ID.
+    names: Cow<'static, [(usize, Cow<'static, str>)]>,
+    /// Recovery rules, keyed by error label, sorted by label. See [`Program::recover`].
+    recovery: Cow<'static, [(usize, usize)]>,
+}
 
 /// Program is instanciated by default without arguments, so define
 /// a Default implementation.
@@ -112,11 +249,48 @@ impl Program {
     /// assert_eq!(p.size(), 0);
     /// ```
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self::from(Vec::new())
+    }
+
+    /// Instanciate a [`Program`] borrowing its [`Instr`], its rule names (see
+    /// [`Program::name_rule`]) and its recovery rules (see [`Program::recover`]) from
+    /// static arrays, names and recovery rules being sorted by ID and error label.
+    /// Unlike [`Program::from`], usable in constants and statics.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::borrow::Cow;
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// use crate::ripeg::vm::VM;
+    /// static DIGITS: Program = Program::from_static(
+    ///     &[
+    ///         Instr::Call(2),
+    ///         Instr::End,
+    ///         Instr::Label(0),
+    ///         Instr::Span(NormalSet::DIGIT),
+    ///         Instr::Return,
+    ///     ],
+    ///     &[(0, Cow::Borrowed("Digits"))],
+    ///     &[],
+    /// );
+    /// assert_eq!(DIGITS.rule_name(0), Some("Digits"));
+    /// assert!(DIGITS.verify().is_ok());
+    /// assert_eq!(VM::new(&DIGITS).exec(b"42a").length, 2);
+    /// ```
+    pub const fn from_static(
+        code: &'static [Instr],
+        names: &'static [(usize, Cow<'static, str>)],
+        recovery: &'static [(usize, usize)],
+    ) -> Program {
+        Program {
+            code: Cow::Borrowed(code),
+            names: Cow::Borrowed(names),
+            recovery: Cow::Borrowed(recovery),
+        }
     }
 
     /// Size of a Program (# of Instr minus Label and Nop)
-    /// Likely to change as Label will probably become a HashMap<Label, Program[index]
     ///
     /// # Examples
     /// ```
@@ -126,7 +300,7 @@ impl Program {
     /// ```
     pub fn size(&self) -> usize {
         let mut s = 0;
-        for i in &self.0 {
+        for i in self.code.iter() {
             match i {
                 Instr::Label(_) | Instr::Nop => continue,
                 _ => s += 1,
@@ -134,4 +308,1271 @@ impl Program {
         }
         s
     }
+
+    /// Appends an [`Instr`] at the end of a [`Program`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let mut p = Program::new();
+    /// p.push(Instr::Char(b'a'));
+    /// p.push(Instr::Nop);
+    /// assert_eq!(p.len(), 2);
+    /// assert_eq!(p.size(), 1);
+    /// ```
+    pub fn push(&mut self, i: Instr) {
+        self.code.to_mut().push(i);
+    }
+
+    /// Number of [`Instr`] in a [`Program`], Label and Nop included.
+    /// Jump targets are indexes in the range `0..len()`.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let p = Program::from(vec![Instr::Label(0), Instr::Char(b'a'), Instr::End]);
+    /// assert_eq!(p.len(), 3);
+    /// assert_eq!(p.size(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Returns true if a [`Program`] holds no [`Instr`] at all
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let mut p = Program::new();
+    /// assert!(p.is_empty());
+    /// p.push(Instr::End);
+    /// assert!(!p.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+
+    /// Returns the [`Instr`] at index, or None if index is out of the [`Program`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let p = Program::from(vec![Instr::Char(b'a'), Instr::End]);
+    /// assert!(matches!(p.get(0), Some(Instr::Char(b'a'))));
+    /// assert!(matches!(p.get(1), Some(Instr::End)));
+    /// assert!(p.get(2).is_none());
+    /// ```
+    pub fn get(&self, index: usize) -> Option<&Instr> {
+        self.code.get(index)
+    }
+
+    /// Names the rule starting at the [`Instr::Label`] of ID id, the name being
+    /// reported by [`crate::vm::Failure`] when the rule fails.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let mut p = Program::from(vec![Instr::Label(3), Instr::Return]);
+    /// p.name_rule(3, "Digit");
+    /// assert_eq!(p.rule_name(3), Some("Digit"));
+    /// ```
+    pub fn name_rule(&mut self, id: usize, name: &str) {
+        let name = Cow::Owned(name.to_owned());
+        match self.names.binary_search_by_key(&id, |(id, _)| *id) {
+            Ok(k) => self.names.to_mut()[k].1 = name,
+            Err(k) => self.names.to_mut().insert(k, (id, name)),
+        }
+    }
+
+    /// Returns the name of the rule starting at the [`Instr::Label`] of ID id, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::grammar::Grammar;
+    /// use crate::ripeg::pattern::{literal, non_term};
+    /// let mut g = Grammar::new("A");
+    /// g.add("A", non_term("B"));
+    /// g.add("B", literal("b"));
+    /// let p = g.compile().unwrap();
+    /// assert_eq!(p.rule_name(1), Some("B"));
+    /// assert_eq!(p.rule_name(2), None);
+    /// ```
+    pub fn rule_name(&self, id: usize) -> Option<&str> {
+        let k = self.names.binary_search_by_key(&id, |(id, _)| *id).ok()?;
+        Some(&self.names[k].1)
+    }
+
+    /// Sets the rule called by [`Instr::Throw`] when it throws error label label.
+    ///
+    /// Like the argument of [`Instr::Call`], rule is the ID of the [`Instr::Label`] marking
+    /// the recovery rule until [`Program::resolve_labels`] replaces it by its index.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let mut p = Program::from(vec![Instr::Throw(1), Instr::End, Instr::Label(9), Instr::Return]);
+    /// p.recover(1, 9);
+    /// assert_eq!(p.recovery(1), Some(9));
+    /// p.resolve_labels().unwrap();
+    /// assert_eq!(p.recovery(1), Some(2));
+    /// assert_eq!(p.recovery(2), None);
+    /// ```
+    pub fn recover(&mut self, label: usize, rule: usize) {
+        match self
+            .recovery
+            .binary_search_by_key(&label, |(label, _)| *label)
+        {
+            Ok(k) => self.recovery.to_mut()[k].1 = rule,
+            Err(k) => self.recovery.to_mut().insert(k, (label, rule)),
+        }
+    }
+
+    /// Returns the recovery rule of error label label, if any. See [`Program::recover`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::Program;
+    /// let mut p = Program::new();
+    /// assert_eq!(p.recovery(0), None);
+    /// p.recover(0, 4);
+    /// assert_eq!(p.recovery(0), Some(4));
+    /// ```
+    pub fn recovery(&self, label: usize) -> Option<usize> {
+        let k = self
+            .recovery
+            .binary_search_by_key(&label, |(label, _)| *label)
+            .ok()?;
+        Some(self.recovery[k].1)
+    }
+
+    /// Returns the listing of a [`Program`], see its [`fmt::Display`] implementation.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::re::parse;
+    /// let p = parse("
+    ///     Pair <- Key '=' Key
+    ///     Key  <- [a-z]+
+    /// ").unwrap().compile().unwrap();
+    /// assert_eq!(p.disassemble(), "   0  Call 2  -- Pair
+    ///    1  End
+    ///    2  Label 0 Pair
+    ///    3  Call 7  -- Key
+    ///    4  Char '='
+    ///    5  Call 7  -- Key
+    ///    6  Return
+    ///    7  Label 1 Key
+    ///    8  Set [a-z]
+    ///    9  Span [a-z]
+    ///   10  Return
+    ///   11  End
+    /// ");
+    /// ```
+    pub fn disassemble(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns the name of the rule whose [`Instr::Label`] is at index, if any.
+    fn rule_at(&self, index: usize) -> Option<&str> {
+        match self.code.get(index) {
+            Some(Instr::Label(id)) => self.rule_name(*id),
+            _ => None,
+        }
+    }
+
+    /// Encodes a [`Program`] into bytes, to be decoded by [`Program::decode`].
+    ///
+    /// The encoding starts with [`MAGIC`] and [`VERSION`] as a little endian u16,
+    /// followed by the number of [`Instr`] and the [`Instr`] themselves, then the rule
+    /// names (see [`Program::rule_name`]) and the recovery rules (see [`Program::recover`]).
+    /// Integers are encoded as unsigned LEB128, names as their length followed by their
+    /// UTF-8 u8. Each [`Instr`] is encoded as its opcode followed by its arguments, a
+    /// [`NormalSet`] being a 32 u8 bitmap, character c being bit c % 8 of u8 c / 8. The
+    /// opcode is the index of the variant in alphabetical order (0 for [`Instr::Any`] to
+    /// 26 for [`Instr::Throw`]) at the time [`VERSION`] 1 was defined, later variants
+    /// being numbered from 27 for [`Instr::LeftCall`] on.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let p = Program::from(vec![Instr::Char(b'a'), Instr::Jump(300), Instr::End]);
+    /// let bytes = p.encode();
+    /// assert_eq!(&bytes[..6], b"RPEG\x02\x00");
+    /// assert_eq!(&bytes[6..], &[3, 5, b'a', 12, 0xac, 0x02, 8, 0, 0]);
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        encode_usize(&mut bytes, self.code.len());
+        for i in self.code.iter() {
+            encode_instr(&mut bytes, i);
+        }
+        encode_usize(&mut bytes, self.names.len());
+        for (id, name) in self.names.iter() {
+            encode_usize(&mut bytes, *id);
+            encode_usize(&mut bytes, name.len());
+            bytes.extend_from_slice(name.as_bytes());
+        }
+        encode_usize(&mut bytes, self.recovery.len());
+        for (label, rule) in self.recovery.iter() {
+            encode_usize(&mut bytes, *label);
+            encode_usize(&mut bytes, *rule);
+        }
+        bytes
+    }
+
+    /// Decodes a [`Program`] encoded by [`Program::encode`], with any [`VERSION`] up to
+    /// the current one.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Error, Program};
+    /// use crate::ripeg::re::parse;
+    /// use crate::ripeg::vm::VM;
+    /// let p = parse("Number <- [0-9]+ ('.' [0-9]+)?").unwrap().compile().unwrap();
+    /// let bytes = p.encode();
+    /// let q = Program::decode(&bytes).unwrap();
+    /// assert_eq!(q.len(), p.len());
+    /// assert_eq!(q.rule_name(0), Some("Number"));
+    /// assert_eq!(VM::new(&q).exec(b"3.14").length, 4);
+    /// assert_eq!(q.encode(), bytes);
+    /// assert!(matches!(Program::decode(b"PEG!"), Err(Error::BadMagic)));
+    /// assert!(matches!(Program::decode(b"RPEG\x03\x00"), Err(Error::UnsupportedVersion(3))));
+    /// assert!(matches!(Program::decode(&bytes[..bytes.len() - 1]), Err(Error::Truncated)));
+    /// ```
+    ///
+    /// [`Instr::LeftCall`] does not exist in version 1 encodings:
+    /// ```
+    /// use crate::ripeg::isa::{Error, Instr, Program};
+    /// let p = Program::from(vec![Instr::Call(2), Instr::End, Instr::LeftCall(0, 0)]);
+    /// let mut bytes = p.encode();
+    /// assert_eq!(Program::decode(&bytes).unwrap().len(), 3);
+    /// bytes[4] = 1;
+    /// assert!(matches!(Program::decode(&bytes), Err(Error::InvalidOpcode(10))));
+    /// let p = Program::from(vec![Instr::Call(2), Instr::End, Instr::Return]);
+    /// let mut bytes = p.encode();
+    /// bytes[4] = 1;
+    /// assert_eq!(Program::decode(&bytes).unwrap().len(), 3);
+    /// ```
+    pub fn decode(bytes: &[u8]) -> Result<Program, Error> {
+        let mut d = Decoder {
+            bytes,
+            pos: 0,
+            version: VERSION,
+        };
+        if d.take(MAGIC.len())? != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        let version = d.take(2)?;
+        d.version = u16::from_le_bytes([version[0], version[1]]);
+        if !(1..=VERSION).contains(&d.version) {
+            return Err(Error::UnsupportedVersion(d.version));
+        }
+        let n = d.usize()?;
+        let mut code = Vec::new();
+        for _ in 0..n {
+            code.push(d.instr()?);
+        }
+        let mut program = Program::from(code);
+        for _ in 0..d.usize()? {
+            let id = d.usize()?;
+            let len = d.usize()?;
+            let at = d.pos;
+            let name = std::str::from_utf8(d.take(len)?).map_err(|_| Error::Corrupted(at))?;
+            program.name_rule(id, name);
+        }
+        for _ in 0..d.usize()? {
+            let label = d.usize()?;
+            program.recover(label, d.usize()?);
+        }
+        if d.pos != bytes.len() {
+            return Err(Error::Corrupted(d.pos));
+        }
+        Ok(program)
+    }
+
+    /// Replaces [`Instr::Label`] IDs by the index of the matching [`Instr::Label`].
+    ///
+    /// Before resolution, the target of every [`Instr::Call`] and [`Instr::LeftCall`] is
+    /// the ID of the [`Instr::Label`] marking the called rule, as emitted for
+    /// non-terminals. After resolution it is the index of that [`Instr::Label`] in the
+    /// [`Program`], which can then be executed. Recovery rules set by [`Program::recover`]
+    /// are resolved the same way. Other jump targets are always indexes and are left
+    /// untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Error, Instr, Program};
+    /// let mut p = Program::from(vec![
+    ///     Instr::Call(7),
+    ///     Instr::End,
+    ///     Instr::Label(7),
+    ///     Instr::Char(b'a'),
+    ///     Instr::Return,
+    /// ]);
+    /// p.resolve_labels().unwrap();
+    /// assert!(matches!(p.get(0), Some(Instr::Call(2))));
+    /// let mut p = Program::from(vec![Instr::Call(3), Instr::End]);
+    /// assert!(matches!(p.resolve_labels(), Err(Error::UndefinedLabel(3))));
+    /// ```
+    pub fn resolve_labels(&mut self) -> Result<(), Error> {
+        let mut labels = HashMap::new();
+        for (index, i) in self.code.iter().enumerate() {
+            if let Instr::Label(id) = i {
+                if labels.insert(*id, index).is_some() {
+                    return Err(Error::DuplicateLabel(*id));
+                }
+            }
+        }
+        for i in self.code.to_mut().iter_mut() {
+            if let Instr::Call(l) | Instr::LeftCall(l, _) = i {
+                *l = *labels.get(l).ok_or(Error::UndefinedLabel(*l))?;
+            }
+        }
+        for (_, l) in self.recovery.to_mut().iter_mut() {
+            *l = *labels.get(l).ok_or(Error::UndefinedLabel(*l))?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a [`Program`] can be executed by [`crate::vm::VM`] without panicking.
+    ///
+    /// Every path from the first [`Instr`], and from every called rule, is followed while
+    /// keeping track of the backtrack and memoization entries pushed on the stack, and of
+    /// the open captures. The [`Program`] is rejected if:
+    /// - a jump target is out of the [`Program`], or a path runs past its last [`Instr`],
+    /// - an [`Instr::Call`], an [`Instr::LeftCall`] or a recovery rule does not target an
+    ///   [`Instr::Label`],
+    /// - several [`Instr::Label`] share the same ID,
+    /// - an [`Instr`] pops an entry or closes a capture which is not on top of the stack,
+    ///   an [`Instr::Return`] leaves entries pushed or captures opened by its rule, an
+    ///   [`Instr::End`] leaves captures open, or an [`Instr`] is reached with different
+    ///   stacks depending on the path,
+    /// - an [`Instr::Return`] is reached outside of a called rule,
+    /// - a path loops back without consuming input, that is without [`Instr::Char`],
+    ///   [`Instr::Set`], [`Instr::Any`], a Test* instruction succeeding, nor a call to a
+    ///   rule which cannot return without consuming input,
+    /// - no [`Instr::End`] is reachable.
+    ///
+    /// Programs compiled from a [`crate::grammar::Grammar`] or a [`crate::pattern::Pattern`]
+    /// always pass, programs read with [`Program::decode`] or [`Program::from_str`]
+    /// should be verified before being executed.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Error, Instr, Program};
+    /// use crate::ripeg::re::parse;
+    /// let mut g = parse("
+    ///     List <- Item (',' Item)* !.
+    ///     Item <- [a-z]+ / '(' List ')'
+    /// ").unwrap();
+    /// assert!(g.compile().unwrap().verify().is_ok());
+    /// g.memoize(true);
+    /// assert!(g.compile().unwrap().verify().is_ok());
+    /// // Commit without Choice
+    /// let p = Program::from(vec![Instr::Char(b'a'), Instr::Commit(2), Instr::End]);
+    /// assert_eq!(p.verify().err().unwrap().to_string(), "instruction 1: expects a backtrack entry");
+    /// // Choice pushed again at each iteration
+    /// let p = Program::from(vec![Instr::Choice(2), Instr::Jump(0), Instr::End]);
+    /// assert!(matches!(p.verify(), Err(Error::Invalid(0, _))));
+    /// // Return in the main program
+    /// let p = Program::from(vec![Instr::Char(b'a'), Instr::Return]);
+    /// assert!(matches!(p.verify(), Err(Error::Invalid(1, _))));
+    /// // Call to an instruction which is not a Label
+    /// let p = Program::from(vec![Instr::Call(2), Instr::End, Instr::Return]);
+    /// assert!(matches!(p.verify(), Err(Error::Invalid(0, _))));
+    /// // Capture closed without being opened
+    /// let p = Program::from(vec![Instr::CaptureEnd, Instr::End]);
+    /// assert!(matches!(p.verify(), Err(Error::Invalid(0, _))));
+    /// // Loop without consuming input
+    /// let p = Program::from(vec![Instr::Choice(3), Instr::Jump(1), Instr::End, Instr::End]);
+    /// assert_eq!(p.verify().err().unwrap().to_string(), "instruction 1: loops without consuming input");
+    /// assert!(matches!(Program::from(vec![Instr::Fail]).verify(), Err(Error::NoEnd)));
+    /// ```
+    pub fn verify(&self) -> Result<(), Error> {
+        let mut ids = HashSet::new();
+        for i in self.code.iter() {
+            if let Instr::Label(id) = i {
+                if !ids.insert(*id) {
+                    return Err(Error::DuplicateLabel(*id));
+                }
+            }
+        }
+        for (label, rule) in self.recovery.iter() {
+            if !matches!(self.code.get(*rule), Some(Instr::Label(_))) {
+                let message = format!("recovery rule of label {} is not a Label", label);
+                return Err(Error::Invalid(*rule, message));
+            }
+        }
+        let mut end = false;
+        // entry points of the main program and of the rules still to be followed.
+        let mut routines = vec![0];
+        let mut followed = HashSet::new();
+        let mut reached = vec![false; self.code.len()];
+        while let Some(start) = routines.pop() {
+            if !followed.insert(start) {
+                continue;
+            }
+            // stack of each reached instruction of the routine.
+            let mut stacks: HashMap<usize, Vec<Frame>> = HashMap::new();
+            let mut paths = vec![(start, Vec::new())];
+            while let Some((index, mut stack)) = paths.pop() {
+                match stacks.get(&index) {
+                    Some(reached) if *reached == stack => continue,
+                    Some(_) => {
+                        let message = "is reached with different stacks".to_owned();
+                        return Err(Error::Invalid(index, message));
+                    }
+                    None => {
+                        stacks.insert(index, stack.clone());
+                        reached[index] = true;
+                    }
+                }
+                let invalid = |message: &str| Error::Invalid(index, message.to_owned());
+                let target = |l: usize| match l < self.code.len() {
+                    true => Ok(l),
+                    false => Err(Error::Invalid(
+                        index,
+                        format!("target {} is out of range", l),
+                    )),
+                };
+                let rule = |l: usize| match self.code.get(target(l)?) {
+                    Some(Instr::Label(_)) => Ok(l),
+                    _ => Err(Error::Invalid(
+                        index,
+                        format!("target {} is not a Label", l),
+                    )),
+                };
+                let next = match index + 1 < self.code.len() {
+                    true => Ok(index + 1),
+                    false => Err(invalid("runs past the end of the program")),
+                };
+                match &self.code[index] {
+                    Instr::Any(_)
+                    | Instr::Char(_)
+                    | Instr::Label(_)
+                    | Instr::Nop
+                    | Instr::Set(_)
+                    | Instr::Span(_) => paths.push((next?, stack)),
+                    Instr::BackCommit(l) | Instr::Commit(l) => {
+                        if stack.pop() != Some(Frame::Backtrack) {
+                            return Err(invalid("expects a backtrack entry"));
+                        }
+                        paths.push((target(*l)?, stack));
+                    }
+                    Instr::Call(l) | Instr::LeftCall(l, _) => {
+                        routines.push(rule(*l)?);
+                        paths.push((next?, stack));
+                    }
+                    Instr::CaptureBegin(_) => {
+                        stack.push(Frame::Capture);
+                        paths.push((next?, stack));
+                    }
+                    Instr::CaptureEnd => {
+                        if stack.pop() != Some(Frame::Capture) {
+                            return Err(invalid("expects an open capture"));
+                        }
+                        paths.push((next?, stack));
+                    }
+                    Instr::Choice(l) => {
+                        paths.push((target(*l)?, stack.clone()));
+                        stack.push(Frame::Backtrack);
+                        paths.push((next?, stack));
+                    }
+                    Instr::End => {
+                        if stack.contains(&Frame::Capture) {
+                            return Err(invalid("ends with captures left open"));
+                        }
+                        end = true;
+                    }
+                    Instr::EndFail | Instr::Fail => {}
+                    Instr::FailTwice => {
+                        if stack.pop() != Some(Frame::Backtrack) {
+                            return Err(invalid("expects a backtrack entry"));
+                        }
+                    }
+                    Instr::Jump(l) => paths.push((target(*l)?, stack)),
+                    Instr::MemoClose => {
+                        if stack.pop() != Some(Frame::Memo) {
+                            return Err(invalid("expects a memoization entry"));
+                        }
+                        paths.push((next?, stack));
+                    }
+                    Instr::MemoOpen(l, _) => {
+                        paths.push((target(*l)?, stack.clone()));
+                        stack.push(Frame::Memo);
+                        paths.push((next?, stack));
+                    }
+                    Instr::PartialCommit(l) => {
+                        if stack.last() != Some(&Frame::Backtrack) {
+                            return Err(invalid("expects a backtrack entry"));
+                        }
+                        paths.push((target(*l)?, stack));
+                    }
+                    Instr::Return => {
+                        if start == 0 {
+                            return Err(invalid("returns outside of a called rule"));
+                        }
+                        if !stack.is_empty() {
+                            return Err(invalid("returns with entries left on the stack"));
+                        }
+                    }
+                    Instr::TestAny(_, l) | Instr::TestChar(_, l) | Instr::TestSet(_, l) => {
+                        paths.push((target(*l)?, stack.clone()));
+                        stack.push(Frame::Backtrack);
+                        paths.push((next?, stack));
+                    }
+                    Instr::TestCharNoChoice(_, l) | Instr::TestSetNoChoice(_, l) => {
+                        paths.push((target(*l)?, stack.clone()));
+                        paths.push((next?, stack));
+                    }
+                    Instr::Throw(label) => {
+                        if let Some(r) = self.recovery(*label) {
+                            routines.push(r);
+                            paths.push((next?, stack));
+                        }
+                    }
+                }
+            }
+        }
+        if !end {
+            return Err(Error::NoEnd);
+        }
+        // rules which can return without consuming input, until none is added.
+        let mut nullable = vec![false; self.code.len()];
+        let mut added = true;
+        while added {
+            added = false;
+            for rule in followed.iter().filter(|rule| **rule != 0) {
+                if !nullable[*rule] && self.returns_empty(*rule, &nullable) {
+                    nullable[*rule] = true;
+                    added = true;
+                }
+            }
+        }
+        // depth first search of a cycle among instructions consuming nothing: 1 marks the
+        // instructions of the current path, 2 those from which no cycle is reachable.
+        let mut marks = vec![0u8; self.code.len()];
+        for start in (0..self.code.len()).filter(|index| reached[*index]) {
+            if marks[start] != 0 {
+                continue;
+            }
+            marks[start] = 1;
+            let mut path = vec![(start, self.empty_successors(start, &nullable))];
+            while let Some((index, successors)) = path.last_mut() {
+                match successors.iter_mut().find_map(Option::take) {
+                    Some(next) if marks[next] == 1 => {
+                        let message = "loops without consuming input".to_owned();
+                        return Err(Error::Invalid(*index, message));
+                    }
+                    Some(next) if marks[next] == 0 => {
+                        marks[next] = 1;
+                        path.push((next, self.empty_successors(next, &nullable)));
+                    }
+                    Some(_) => {}
+                    None => {
+                        marks[*index] = 2;
+                        path.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the indexes of the [`Instr`] which can follow the one at index without
+    /// consuming input, rules marked in nullable returning without consuming input.
+    fn empty_successors(&self, index: usize, nullable: &[bool]) -> [Option<usize>; 2] {
+        let next = Some(index + 1);
+        match &self.code[index] {
+            Instr::Any(0)
+            | Instr::CaptureBegin(_)
+            | Instr::CaptureEnd
+            | Instr::Label(_)
+            | Instr::MemoClose
+            | Instr::Nop
+            | Instr::Span(_) => [next, None],
+            Instr::Any(_) | Instr::Char(_) | Instr::Set(_) => [None, None],
+            Instr::BackCommit(l) | Instr::Commit(l) | Instr::Jump(l) | Instr::PartialCommit(l) => {
+                [Some(*l), None]
+            }
+            Instr::Call(l) | Instr::LeftCall(l, _) => match nullable[*l] {
+                true => [next, None],
+                false => [None, None],
+            },
+            Instr::Choice(l) | Instr::MemoOpen(l, _) | Instr::TestAny(0, l) => [Some(*l), next],
+            Instr::End | Instr::EndFail | Instr::Fail | Instr::FailTwice | Instr::Return => {
+                [None, None]
+            }
+            Instr::TestAny(_, l)
+            | Instr::TestChar(_, l)
+            | Instr::TestCharNoChoice(_, l)
+            | Instr::TestSet(_, l)
+            | Instr::TestSetNoChoice(_, l) => [Some(*l), None],
+            Instr::Throw(label) => match self.recovery(*label) {
+                Some(rule) if nullable[rule] => [next, None],
+                _ => [None, None],
+            },
+        }
+    }
+
+    /// Tells whether the rule starting at index rule can reach its [`Instr::Return`]
+    /// without consuming input, rules marked in nullable returning without consuming input.
+    fn returns_empty(&self, rule: usize, nullable: &[bool]) -> bool {
+        let mut reached = vec![false; self.code.len()];
+        reached[rule] = true;
+        let mut pending = vec![rule];
+        while let Some(index) = pending.pop() {
+            if let Instr::Return = self.code[index] {
+                return true;
+            }
+            for next in self.empty_successors(index, nullable).into_iter().flatten() {
+                if !reached[next] {
+                    reached[next] = true;
+                    pending.push(next);
+                }
+            }
+        }
+        false
+    }
+
+    /// Rewrites a [`Program`] into an equivalent one using specialized instructions.
+    ///
+    /// The following rewrites are applied, in this order:
+    /// - [`Instr::Set`], [`Instr::TestSet`] and [`Instr::TestSetNoChoice`] of a single u8
+    ///   become [`Instr::Char`], [`Instr::TestChar`] and [`Instr::TestCharNoChoice`],
+    /// - `L: Choice E; Set s; Commit L; E:` loops become `Span s`, `Char c` loops too,
+    /// - `L: Choice E; p; Commit L; E:` loops become `Choice E; p; PartialCommit L+1`,
+    /// - `Choice L; Char c` heads become `TestChar c L`, the same goes for [`Instr::Set`]
+    ///   and [`Instr::Any`],
+    /// - jumps to [`Instr::Jump`] go directly to the final target, [`Instr::Jump`] to
+    ///   [`Instr::End`], [`Instr::EndFail`], [`Instr::Fail`] or [`Instr::Return`] is
+    ///   replaced by the target, and [`Instr::Jump`] to the following [`Instr`] is removed,
+    /// - [`Instr::Nop`] and [`Instr::Label`] targeted by no [`Instr::Call`],
+    ///   [`Instr::LeftCall`] nor recovery rule are removed.
+    ///
+    /// [`crate::pattern::Pattern::compile`] and [`crate::grammar::Grammar::compile`] return
+    /// optimized programs.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// use crate::ripeg::vm::VM;
+    /// // [0-9]* 'a'* ('x' / 'y')
+    /// let mut p = Program::from(vec![
+    ///     Instr::Choice(3),
+    ///     Instr::Set(NormalSet::range(b'0', b'9')),
+    ///     Instr::Commit(0),
+    ///     Instr::Choice(6),
+    ///     Instr::Char(b'a'),
+    ///     Instr::Commit(3),
+    ///     Instr::Nop,
+    ///     Instr::Choice(11),
+    ///     Instr::Set(NormalSet::range(b'x', b'x')),
+    ///     Instr::Commit(10),
+    ///     Instr::Jump(12),
+    ///     Instr::Char(b'y'),
+    ///     Instr::End,
+    /// ]);
+    /// p.optimize();
+    /// assert_eq!(p.disassemble(), "   0  Span [0-9]
+    ///    1  Span [a]
+    ///    2  TestChar 'x' 5
+    ///    3  Commit 6
+    ///    4  End
+    ///    5  Char 'y'
+    ///    6  End
+    /// ");
+    /// let mut vm = VM::new(&p);
+    /// assert_eq!(vm.exec(b"12aay").length, 5);
+    /// assert_eq!(vm.exec(b"x").length, 1);
+    /// ```
+    ///
+    /// Loops whose body is not a single u8:
+    /// ```
+    /// use crate::ripeg::pattern::{literal, star};
+    /// let p = star(literal("ab")).compile().unwrap();
+    /// assert_eq!(p.disassemble(), "   0  Choice 4
+    ///    1  Char 'a'
+    ///    2  Char 'b'
+    ///    3  PartialCommit 1
+    ///    4  End
+    /// ");
+    /// ```
+    pub fn optimize(&mut self) {
+        for i in self.code.to_mut().iter_mut() {
+            let single = |s: &NormalSet| match s.size() {
+                1 => s.iter().next(),
+                _ => None,
+            };
+            let replacement = match i {
+                Instr::Set(s) => single(s).map(Instr::Char),
+                Instr::TestSet(s, l) => single(s).map(|c| Instr::TestChar(c, *l)),
+                Instr::TestSetNoChoice(s, l) => single(s).map(|c| Instr::TestCharNoChoice(c, *l)),
+                _ => None,
+            };
+            if let Some(r) = replacement {
+                *i = r;
+            }
+        }
+        let targets = self.targets();
+        for k in 0..self.code.len().saturating_sub(2) {
+            let set = match &self.code[k..k + 3] {
+                [Instr::Choice(e), Instr::Set(s), Instr::Commit(l)] if *e == k + 3 && *l == k => *s,
+                [Instr::Choice(e), Instr::Char(c), Instr::Commit(l)] if *e == k + 3 && *l == k => {
+                    NormalSet::new(&[*c])
+                }
+                _ => continue,
+            };
+            if !targets.contains(&(k + 1)) && !targets.contains(&(k + 2)) {
+                self.code.to_mut()[k] = Instr::Span(set);
+                self.code.to_mut()[k + 1] = Instr::Nop;
+                self.code.to_mut()[k + 2] = Instr::Nop;
+            }
+        }
+        for k in 0..self.code.len() {
+            if let Instr::Commit(l) = self.code[k] {
+                if l < k && matches!(self.code[l], Instr::Choice(e) if e == k + 1) {
+                    self.code.to_mut()[k] = Instr::PartialCommit(l + 1);
+                }
+            }
+        }
+        let targets = self.targets();
+        for k in 0..self.code.len().saturating_sub(1) {
+            let l = match self.code[k] {
+                Instr::Choice(l) if !targets.contains(&(k + 1)) => l,
+                _ => continue,
+            };
+            let test = match &self.code[k + 1] {
+                Instr::Any(n) => Instr::TestAny(*n, l),
+                Instr::Char(c) => Instr::TestChar(*c, l),
+                Instr::Set(s) => Instr::TestSet(*s, l),
+                _ => continue,
+            };
+            self.code.to_mut()[k] = test;
+            self.code.to_mut()[k + 1] = Instr::Nop;
+        }
+        let labels = self.called();
+        let removed = |i: &Instr, index: usize| match i {
+            Instr::Nop => true,
+            Instr::Label(_) => !labels.contains(&index),
+            _ => false,
+        };
+        for k in 0..self.code.len() {
+            let mut l = match self.code[k] {
+                Instr::Call(_) | Instr::LeftCall(..) => continue,
+                ref i => match target(i) {
+                    Some(l) => l,
+                    None => continue,
+                },
+            };
+            // follows jumps, at most once per instruction to stop on loops.
+            for _ in 0..self.code.len() {
+                match self.code.get(l) {
+                    Some(Instr::Jump(m)) if *m != l => l = *m,
+                    _ => break,
+                }
+            }
+            if let Some(t) = target_mut(&mut self.code.to_mut()[k]) {
+                *t = l;
+            }
+            if let Instr::Jump(l) = self.code[k] {
+                self.code.to_mut()[k] = match self.code.get(l) {
+                    Some(Instr::End) => Instr::End,
+                    Some(Instr::EndFail) => Instr::EndFail,
+                    Some(Instr::Fail) => Instr::Fail,
+                    Some(Instr::Return) => Instr::Return,
+                    _ if l > k && (k + 1..l).all(|j| removed(&self.code[j], j)) => Instr::Nop,
+                    _ => continue,
+                };
+            }
+        }
+        // new index of each instruction, removed ones taking the index of the next one.
+        let mut index = Vec::with_capacity(self.code.len() + 1);
+        let mut kept = 0;
+        for (k, i) in self.code.iter().enumerate() {
+            index.push(kept);
+            if !removed(i, k) {
+                kept += 1;
+            }
+        }
+        index.push(kept);
+        let code = std::mem::take(&mut self.code).into_owned();
+        self.code = code
+            .into_iter()
+            .enumerate()
+            .filter(|(k, i)| !removed(i, *k))
+            .map(|(_, mut i)| {
+                if let Some(l) = target_mut(&mut i) {
+                    *l = index[*l];
+                }
+                i
+            })
+            .collect::<Vec<_>>()
+            .into();
+        for (_, l) in self.recovery.to_mut().iter_mut() {
+            *l = index[*l];
+        }
+    }
+
+    /// Returns the indexes targeted by an [`Instr`] or a recovery rule.
+    fn targets(&self) -> HashSet<usize> {
+        let mut targets = self.called();
+        targets.extend(self.code.iter().filter_map(target));
+        targets
+    }
+
+    /// Returns the indexes targeted by an [`Instr::Call`], an [`Instr::LeftCall`] or a
+    /// recovery rule.
+    fn called(&self) -> HashSet<usize> {
+        let calls = self.code.iter().filter_map(|i| match i {
+            Instr::Call(l) | Instr::LeftCall(l, _) => Some(*l),
+            _ => None,
+        });
+        calls
+            .chain(self.recovery.iter().map(|(_, rule)| *rule))
+            .collect()
+    }
+}
+
+/// Returns the jump target of i, if any.
+fn target(i: &Instr) -> Option<usize> {
+    match i {
+        Instr::BackCommit(l)
+        | Instr::Call(l)
+        | Instr::Choice(l)
+        | Instr::Commit(l)
+        | Instr::Jump(l)
+        | Instr::LeftCall(l, _)
+        | Instr::MemoOpen(l, _)
+        | Instr::PartialCommit(l)
+        | Instr::TestAny(_, l)
+        | Instr::TestChar(_, l)
+        | Instr::TestCharNoChoice(_, l)
+        | Instr::TestSet(_, l)
+        | Instr::TestSetNoChoice(_, l) => Some(*l),
+        _ => None,
+    }
+}
+
+/// Returns a mutable reference to the jump target of i, if any.
+fn target_mut(i: &mut Instr) -> Option<&mut usize> {
+    match i {
+        Instr::BackCommit(l)
+        | Instr::Call(l)
+        | Instr::Choice(l)
+        | Instr::Commit(l)
+        | Instr::Jump(l)
+        | Instr::LeftCall(l, _)
+        | Instr::MemoOpen(l, _)
+        | Instr::PartialCommit(l)
+        | Instr::TestAny(_, l)
+        | Instr::TestChar(_, l)
+        | Instr::TestCharNoChoice(_, l)
+        | Instr::TestSet(_, l)
+        | Instr::TestSetNoChoice(_, l) => Some(l),
+        _ => None,
+    }
+}
+
+/// Entry pushed on the stack of the [`crate::vm::VM`], or capture open, as tracked by
+/// [`Program::verify`].
+#[derive(Clone, PartialEq)]
+enum Frame {
+    /// Pushed by [`Instr::Choice`] and Test* instructions.
+    Backtrack,
+    /// Pushed by [`Instr::MemoOpen`].
+    Memo,
+    /// Opened by [`Instr::CaptureBegin`].
+    Capture,
+}
+
+/// Formats a [`Program`] as a listing: one line per [`Instr`], starting with its index.
+///
+/// The name of a rule follows its [`Instr::Label`], and is repeated in a `--` comment
+/// after each [`Instr::Call`] or [`Instr::LeftCall`] to it. Each recovery rule (see
+/// [`Program::recover`]) is listed after the [`Instr`] as `recover` followed by the error
+/// label and the rule index. The listing can be parsed back with [`Program::from_str`].
+impl fmt::Display for Program {
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::{NormalSet, Set};
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let mut p = Program::from(vec![
+    ///     Instr::Throw(1),
+    ///     Instr::End,
+    ///     Instr::Label(0),
+    ///     Instr::Span(NormalSet::new(&[b' '])),
+    ///     Instr::Return,
+    /// ]);
+    /// p.name_rule(0, "Skip");
+    /// p.recover(1, 0);
+    /// p.resolve_labels().unwrap();
+    /// assert_eq!(p.to_string(), "   0  Throw 1
+    ///    1  End
+    ///    2  Label 0 Skip
+    ///    3  Span [\\x20]
+    ///    4  Return
+    /// recover 1 2  -- Skip
+    /// ");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, i) in self.code.iter().enumerate() {
+            write!(f, "{:4}  {}", index, i)?;
+            match i {
+                Instr::Label(id) => {
+                    if let Some(name) = self.rule_name(*id) {
+                        write!(f, " {}", name)?;
+                    }
+                }
+                Instr::Call(l) | Instr::LeftCall(l, _) => {
+                    if let Some(name) = self.rule_at(*l) {
+                        write!(f, "  -- {}", name)?;
+                    }
+                }
+                _ => {}
+            }
+            writeln!(f)?;
+        }
+        let mut recovery: Vec<_> = self.recovery.iter().collect();
+        recovery.sort();
+        for (label, rule) in recovery {
+            write!(f, "recover {} {}", label, rule)?;
+            if let Some(name) = self.rule_at(*rule) {
+                write!(f, "  -- {}", name)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a listing written by the [`fmt::Display`] implementation of [`Program`].
+impl FromStr for Program {
+    type Err = Error;
+
+    /// `--` comments, starting a line or following a space, and empty lines are ignored,
+    /// the index starting each line must be the index of its [`Instr`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::Program;
+    /// use crate::ripeg::re::parse;
+    /// use crate::ripeg::vm::VM;
+    /// let p = parse("Word <- [a-zA-Z_]+ ('-' / ' ')?").unwrap().compile().unwrap();
+    /// let q: Program = p.disassemble().parse().unwrap();
+    /// assert_eq!(q.disassemble(), p.disassemble());
+    /// assert_eq!(q.rule_name(0), Some("Word"));
+    /// assert_eq!(VM::new(&q).exec(b"hello world").length, 6);
+    /// let e = "0  Char 'a'\n2  End".parse::<Program>().err().unwrap();
+    /// assert_eq!(e.to_string(), "line 2: expected index 1");
+    /// ```
+    ///
+    /// Sets may contain `--`:
+    /// ```
+    /// use crate::ripeg::charset::{NormalSet, Set};
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let p = Program::from(vec![Instr::Set(NormalSet::new(b"-./")), Instr::End]);
+    /// let listing = p.to_string();
+    /// assert!(listing.contains(r"Set [\--/]"));
+    /// let q: Program = listing.parse().unwrap();
+    /// assert!(matches!(q.get(0), Some(Instr::Set(s)) if *s == NormalSet::new(b"-./")));
+    /// assert_eq!(q.to_string(), listing);
+    /// ```
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut program = Program::new();
+        for (n, line) in text.lines().enumerate() {
+            // Within a token, as in the Set [\--/], -- does not start a comment.
+            let mut tokens = line
+                .split_whitespace()
+                .take_while(|token| !token.starts_with("--"));
+            let error = |message: &str| Error::Listing(n + 1, message.to_owned());
+            let first = match tokens.next() {
+                Some(first) => first,
+                None => continue,
+            };
+            if first == "recover" {
+                let label = parse_usize(tokens.next()).ok_or_else(|| error("expected label"))?;
+                let rule = parse_usize(tokens.next()).ok_or_else(|| error("expected index"))?;
+                program.recover(label, rule);
+            } else {
+                if first.parse() != Ok(program.len()) {
+                    return Err(error(&format!("expected index {}", program.len())));
+                }
+                let name = tokens.next().ok_or_else(|| error("expected instruction"))?;
+                let i = parse_instr(name, &mut tokens)
+                    .ok_or_else(|| error(&format!("invalid instruction '{}'", name)))?;
+                if let Instr::Label(id) = i {
+                    if let Some(rule) = tokens.next() {
+                        program.name_rule(id, rule);
+                    }
+                }
+                program.push(i);
+            }
+            if let Some(token) = tokens.next() {
+                return Err(error(&format!("unexpected '{}'", token)));
+            }
+        }
+        Ok(program)
+    }
+}
+
+/// Builds a [`Program`] from a vector of [`Instr`].
+impl From<Vec<Instr>> for Program {
+    /// Jump targets of the [`Instr`] are indexes in the given vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// let p = Program::from(vec![Instr::Char(b'a'), Instr::End]);
+    /// assert_eq!(p.size(), 2);
+    /// ```
+    fn from(instrs: Vec<Instr>) -> Self {
+        Self {
+            code: Cow::Owned(instrs),
+            names: Cow::Borrowed(&[]),
+            recovery: Cow::Borrowed(&[]),
+        }
+    }
+}
+
+/// Appends n to bytes as unsigned LEB128.
+fn encode_usize(bytes: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        bytes.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+/// Appends set to bytes as a 32 u8 bitmap.
+fn encode_set(bytes: &mut Vec<u8>, set: &NormalSet) {
+    let mut bitmap = [0u8; 32];
+    for c in 0..=255u8 {
+        if set.has(c) {
+            bitmap[c as usize / 8] |= 1 << (c % 8);
+        }
+    }
+    bytes.extend_from_slice(&bitmap);
+}
+
+/// Appends the opcode and arguments of i to bytes.
+fn encode_instr(bytes: &mut Vec<u8>, i: &Instr) {
+    match i {
+        Instr::Any(n) => encode_args(bytes, 0, &[*n]),
+        Instr::BackCommit(l) => encode_args(bytes, 1, &[*l]),
+        Instr::Call(l) => encode_args(bytes, 2, &[*l]),
+        Instr::CaptureBegin(id) => encode_args(bytes, 3, &[*id]),
+        Instr::CaptureEnd => bytes.push(4),
+        Instr::Char(c) => bytes.extend_from_slice(&[5, *c]),
+        Instr::Choice(l) => encode_args(bytes, 6, &[*l]),
+        Instr::Commit(l) => encode_args(bytes, 7, &[*l]),
+        Instr::End => bytes.push(8),
+        Instr::EndFail => bytes.push(9),
+        Instr::Fail => bytes.push(10),
+        Instr::FailTwice => bytes.push(11),
+        Instr::Jump(l) => encode_args(bytes, 12, &[*l]),
+        Instr::Label(id) => encode_args(bytes, 13, &[*id]),
+        Instr::MemoClose => bytes.push(14),
+        Instr::MemoOpen(l, id) => encode_args(bytes, 15, &[*l, *id]),
+        Instr::Nop => bytes.push(16),
+        Instr::PartialCommit(l) => encode_args(bytes, 17, &[*l]),
+        Instr::Return => bytes.push(18),
+        Instr::Set(s) => {
+            bytes.push(19);
+            encode_set(bytes, s);
+        }
+        Instr::Span(s) => {
+            bytes.push(20);
+            encode_set(bytes, s);
+        }
+        Instr::TestAny(n, l) => encode_args(bytes, 21, &[*n, *l]),
+        Instr::TestChar(c, l) => {
+            bytes.extend_from_slice(&[22, *c]);
+            encode_usize(bytes, *l);
+        }
+        Instr::TestCharNoChoice(c, l) => {
+            bytes.extend_from_slice(&[23, *c]);
+            encode_usize(bytes, *l);
+        }
+        Instr::TestSet(s, l) => {
+            bytes.push(24);
+            encode_set(bytes, s);
+            encode_usize(bytes, *l);
+        }
+        Instr::TestSetNoChoice(s, l) => {
+            bytes.push(25);
+            encode_set(bytes, s);
+            encode_usize(bytes, *l);
+        }
+        Instr::Throw(label) => encode_args(bytes, 26, &[*label]),
+        Instr::LeftCall(l, k) => encode_args(bytes, 27, &[*l, *k]),
+    }
+}
+
+/// Appends opcode followed by its integer arguments to bytes.
+fn encode_args(bytes: &mut Vec<u8>, opcode: u8, args: &[usize]) {
+    bytes.push(opcode);
+    for a in args {
+        encode_usize(bytes, *a);
+    }
+}
+
+/// Reads the values of an encoded [`Program`].
+struct Decoder<'a> {
+    /// Encoded [`Program`].
+    bytes: &'a [u8],
+    /// Offset of the next value in bytes.
+    pos: usize,
+    /// [`VERSION`] of the encoding.
+    version: u16,
+}
+
+impl<'a> Decoder<'a> {
+    /// Reads the next n u8.
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let end = self.pos.checked_add(n).ok_or(Error::Truncated)?;
+        let taken = self.bytes.get(self.pos..end).ok_or(Error::Truncated)?;
+        self.pos = end;
+        Ok(taken)
+    }
+
+    /// Reads the next u8.
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Reads the next unsigned LEB128 integer.
+    fn usize(&mut self) -> Result<usize, Error> {
+        let at = self.pos;
+        let mut n = 0usize;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            let bits = (b & 0x7f) as usize;
+            if shift >= usize::BITS || (bits << shift) >> shift != bits {
+                return Err(Error::Corrupted(at));
+            }
+            n |= bits << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads the next 32 u8 bitmap.
+    fn set(&mut self) -> Result<NormalSet, Error> {
+        let bitmap = self.take(32)?;
+        let chars: Vec<u8> = (0..=255u8)
+            .filter(|c| bitmap[*c as usize / 8] & (1 << (c % 8)) != 0)
+            .collect();
+        Ok(NormalSet::new(&chars))
+    }
+
+    /// Reads the next [`Instr`].
+    fn instr(&mut self) -> Result<Instr, Error> {
+        let at = self.pos;
+        Ok(match self.u8()? {
+            0 => Instr::Any(self.usize()?),
+            1 => Instr::BackCommit(self.usize()?),
+            2 => Instr::Call(self.usize()?),
+            3 => Instr::CaptureBegin(self.usize()?),
+            4 => Instr::CaptureEnd,
+            5 => Instr::Char(self.u8()?),
+            6 => Instr::Choice(self.usize()?),
+            7 => Instr::Commit(self.usize()?),
+            8 => Instr::End,
+            9 => Instr::EndFail,
+            10 => Instr::Fail,
+            11 => Instr::FailTwice,
+            12 => Instr::Jump(self.usize()?),
+            13 => Instr::Label(self.usize()?),
+            14 => Instr::MemoClose,
+            15 => Instr::MemoOpen(self.usize()?, self.usize()?),
+            16 => Instr::Nop,
+            17 => Instr::PartialCommit(self.usize()?),
+            18 => Instr::Return,
+            19 => Instr::Set(self.set()?),
+            20 => Instr::Span(self.set()?),
+            21 => Instr::TestAny(self.usize()?, self.usize()?),
+            22 => Instr::TestChar(self.u8()?, self.usize()?),
+            23 => Instr::TestCharNoChoice(self.u8()?, self.usize()?),
+            24 => Instr::TestSet(self.set()?, self.usize()?),
+            25 => Instr::TestSetNoChoice(self.set()?, self.usize()?),
+            26 => Instr::Throw(self.usize()?),
+            27 if self.version >= 2 => Instr::LeftCall(self.usize()?, self.usize()?),
+            _ => return Err(Error::InvalidOpcode(at)),
+        })
+    }
+}
+
+/// Writes u8 c between quotes for a listing, as `\xHH` if it is not a visible ASCII
+/// character or if it is a quote or a backslash.
+fn quote(c: u8) -> String {
+    match c {
+        b'!'..=b'~' if c != b'\'' && c != b'\\' => format!("'{}'", c as char),
+        _ => format!("'\\x{:02x}'", c),
+    }
+}
+
+/// Parses an integer token of a listing.
+fn parse_usize(token: Option<&str>) -> Option<usize> {
+    token?.parse().ok()
+}
+
+/// Parses a u8 token of a listing written by [`quote`].
+fn parse_u8(token: Option<&str>) -> Option<u8> {
+    let inner = token?.strip_prefix('\'')?.strip_suffix('\'')?;
+    match inner.strip_prefix("\\x") {
+        Some(hex) if hex.len() == 2 => u8::from_str_radix(hex, 16).ok(),
+        Some(_) => None,
+        None if inner.len() == 1 => Some(inner.as_bytes()[0]),
+        None => None,
+    }
+}
+
+/// Parses a [`NormalSet`] token of a listing written by [`NormalSet::string`].
+fn parse_set(token: Option<&str>) -> Option<NormalSet> {
+    token?.parse().ok()
+}
+
+/// Parses the arguments of the [`Instr`] named name from tokens.
+fn parse_instr<'a, I: Iterator<Item = &'a str>>(name: &str, tokens: &mut I) -> Option<Instr> {
+    Some(match name {
+        "Any" => Instr::Any(parse_usize(tokens.next())?),
+        "BackCommit" => Instr::BackCommit(parse_usize(tokens.next())?),
+        "Call" => Instr::Call(parse_usize(tokens.next())?),
+        "CaptureBegin" => Instr::CaptureBegin(parse_usize(tokens.next())?),
+        "CaptureEnd" => Instr::CaptureEnd,
+        "Choice" => Instr::Choice(parse_usize(tokens.next())?),
+        "Commit" => Instr::Commit(parse_usize(tokens.next())?),
+        "End" => Instr::End,
+        "EndFail" => Instr::EndFail,
+        "Fail" => Instr::Fail,
+        "FailTwice" => Instr::FailTwice,
+        "Jump" => Instr::Jump(parse_usize(tokens.next())?),
+        "Label" => Instr::Label(parse_usize(tokens.next())?),
+        "LeftCall" => Instr::LeftCall(parse_usize(tokens.next())?, parse_usize(tokens.next())?),
+        "MemoClose" => Instr::MemoClose,
+        "MemoOpen" => Instr::MemoOpen(parse_usize(tokens.next())?, parse_usize(tokens.next())?),
+        "Nop" => Instr::Nop,
+        "PartialCommit" => Instr::PartialCommit(parse_usize(tokens.next())?),
+        "Return" => Instr::Return,
+        "TestAny" => Instr::TestAny(parse_usize(tokens.next())?, parse_usize(tokens.next())?),
+        "Throw" => Instr::Throw(parse_usize(tokens.next())?),
+        "Char" => Instr::Char(parse_u8(tokens.next())?),
+        "TestChar" => Instr::TestChar(parse_u8(tokens.next())?, parse_usize(tokens.next())?),
+        "TestCharNoChoice" => {
+            Instr::TestCharNoChoice(parse_u8(tokens.next())?, parse_usize(tokens.next())?)
+        }
+        "Set" => Instr::Set(parse_set(tokens.next())?),
+        "Span" => Instr::Span(parse_set(tokens.next())?),
+        "TestSet" => Instr::TestSet(parse_set(tokens.next())?, parse_usize(tokens.next())?),
+        "TestSetNoChoice" => {
+            Instr::TestSetNoChoice(parse_set(tokens.next())?, parse_usize(tokens.next())?)
+        }
+        _ => return None,
+    })
 }
@@ -35,5 +35,14 @@
 // Any PR emitting warnings when it comes to documentation won’t be accepted.
 #[warn(missing_docs)]
 #[warn(rustdoc::missing_doc_code_examples)]
+pub mod capture;
 pub mod charset;
+pub mod grammar;
+pub mod input;
 pub mod isa;
+pub mod memo;
+pub mod pattern;
+pub mod re;
+pub mod scan;
+pub mod unicode;
+pub mod vm;
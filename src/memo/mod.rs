@@ -0,0 +1,211 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! memo module provides the packrat memoization table storing the outcome of
+//! rule invocations, filled and used by [`crate::vm::VM`] through
+//! [`crate::isa::Instr::MemoOpen`] and [`crate::isa::Instr::MemoClose`].
+
+use std::collections::BTreeMap;
+
+use crate::capture::Node;
+use crate::vm::Thrown;
+
+/// Outcome of a rule invocation at a given subject position.
+#[derive(Clone)]
+pub struct Entry {
+    /// Number of u8 consumed by the rule, None if the rule failed.
+    pub length: Option<usize>,
+    /// Number of u8 examined by the rule from its starting position, which can be
+    /// greater than length because of lookaheads and failed alternatives.
+    pub examined: usize,
+    /// Captures made by the rule, positions being relative to its starting position.
+    pub captures: Vec<Node>,
+    /// Error labels thrown and recovered by the rule, offsets being relative to its
+    /// starting position.
+    pub errors: Vec<Thrown>,
+}
+
+/// Memoization table, mapping a rule ID and a subject position to an [`Entry`].
+#[derive(Default)]
+pub struct Table {
+    /// Entries, keyed by subject position then rule ID.
+    entries: BTreeMap<(usize, usize), Entry>,
+    /// Greatest examined length of all entries, bounding the entries an edit can affect.
+    max_examined: usize,
+}
+
+/// Methods for a memoization [`Table`]
+impl Table {
+    /// Instanciate a new empty [`Table`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::Table;
+    /// let t = Table::new();
+    /// assert_eq!(t.len(), 0);
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            max_examined: 0,
+        }
+    }
+
+    /// Returns the [`Entry`] of rule id invoked at position pos, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::{Entry, Table};
+    /// let mut t = Table::new();
+    /// t.insert(0, 10, Entry { length: Some(3), examined: 4, captures: Vec::new(), errors: Vec::new() });
+    /// assert_eq!(t.get(0, 10).unwrap().length, Some(3));
+    /// assert!(t.get(1, 10).is_none());
+    /// assert!(t.get(0, 11).is_none());
+    /// ```
+    pub fn get(&self, id: usize, pos: usize) -> Option<&Entry> {
+        self.entries.get(&(pos, id))
+    }
+
+    /// Stores the [`Entry`] of rule id invoked at position pos, replacing any previous one.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::{Entry, Table};
+    /// let mut t = Table::new();
+    /// t.insert(0, 10, Entry { length: Some(3), examined: 4, captures: Vec::new(), errors: Vec::new() });
+    /// t.insert(0, 10, Entry { length: None, examined: 1, captures: Vec::new(), errors: Vec::new() });
+    /// assert_eq!(t.len(), 1);
+    /// assert_eq!(t.get(0, 10).unwrap().length, None);
+    /// ```
+    pub fn insert(&mut self, id: usize, pos: usize, entry: Entry) {
+        self.max_examined = self.max_examined.max(entry.examined);
+        self.entries.insert((pos, id), entry);
+    }
+
+    /// Removes and returns the [`Entry`] of rule id invoked at position pos, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::{Entry, Table};
+    /// let mut t = Table::new();
+    /// t.insert(0, 10, Entry { length: Some(3), examined: 4, captures: Vec::new(), errors: Vec::new() });
+    /// assert_eq!(t.remove(0, 10).unwrap().length, Some(3));
+    /// assert!(t.remove(0, 10).is_none());
+    /// assert!(t.is_empty());
+    /// ```
+    pub fn remove(&mut self, id: usize, pos: usize) -> Option<Entry> {
+        self.entries.remove(&(pos, id))
+    }
+
+    /// Number of [`Entry`] in a [`Table`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::{Entry, Table};
+    /// let mut t = Table::new();
+    /// t.insert(0, 10, Entry { length: Some(3), examined: 4, captures: Vec::new(), errors: Vec::new() });
+    /// t.insert(1, 10, Entry { length: Some(3), examined: 4, captures: Vec::new(), errors: Vec::new() });
+    /// assert_eq!(t.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if a [`Table`] holds no [`Entry`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::{Entry, Table};
+    /// let mut t = Table::new();
+    /// assert!(t.is_empty());
+    /// t.insert(0, 10, Entry { length: Some(3), examined: 4, captures: Vec::new(), errors: Vec::new() });
+    /// assert!(!t.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all [`Entry`] of a [`Table`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::{Entry, Table};
+    /// let mut t = Table::new();
+    /// t.insert(0, 10, Entry { length: Some(3), examined: 4, captures: Vec::new(), errors: Vec::new() });
+    /// t.clear();
+    /// assert!(t.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.max_examined = 0;
+    }
+
+    /// Updates a [`Table`] after old_len u8 of the subject starting at position start
+    /// have been replaced by new_len u8.
+    ///
+    /// Entries whose examined range overlaps the edit are removed, entries located after
+    /// the edit are moved by new_len - old_len, other ones are kept as is. Running the
+    /// [`crate::vm::VM`] again on the edited subject with the [`Table`] then only matches
+    /// again the rules invalidated by the edit.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::Table;
+    /// use crate::ripeg::re::parse;
+    /// use crate::ripeg::vm::VM;
+    /// let mut g = parse("
+    ///     List <- Item (',' Item)*
+    ///     Item <- [a-z]+
+    /// ").unwrap();
+    /// g.memoize(true);
+    /// let p = g.compile().unwrap();
+    /// let mut vm = VM::new(&p);
+    /// let mut table = Table::new();
+    /// assert_eq!(vm.exec_memo(b"ab,cd,ef", &mut table).length, 8);
+    /// assert_eq!(table.get(1, 6).unwrap().length, Some(2));
+    /// // "cd" becomes "xyz"
+    /// table.apply_edit(3, 2, 3);
+    /// assert!(table.get(0, 0).is_none()); // List examined the edited part
+    /// assert_eq!(table.get(1, 0).unwrap().length, Some(2)); // "ab" is untouched
+    /// assert!(table.get(1, 3).is_none()); // "cd" is gone
+    /// assert_eq!(table.get(1, 7).unwrap().length, Some(2)); // "ef" moved
+    /// assert_eq!(vm.exec_memo(b"ab,xyz,ef", &mut table).length, 9);
+    /// assert_eq!(table.get(1, 3).unwrap().length, Some(3));
+    /// ```
+    pub fn apply_edit(&mut self, start: usize, old_len: usize, new_len: usize) {
+        let end = start + old_len;
+        // entries starting after the edit are moved, the ones inside are dropped.
+        let after = self.entries.split_off(&(end, 0));
+        self.entries.split_off(&(start, 0));
+        // entries starting before the edit are dropped if they examined it.
+        let first = start.saturating_sub(self.max_examined);
+        let stale: Vec<(usize, usize)> = self
+            .entries
+            .range((first, 0)..)
+            .filter(|((pos, _), entry)| pos + entry.examined > start)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in stale {
+            self.entries.remove(&key);
+        }
+        self.entries.extend(
+            after
+                .into_iter()
+                .map(|((pos, id), entry)| ((pos - old_len + new_len, id), entry)),
+        );
+    }
+}
@@ -0,0 +1,964 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! pattern module provides composable constructors for parsing expressions
+//! and their compilation into a [`Program`].
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::charset::{NormalSet, Set};
+use crate::isa::{Instr, Program};
+
+/// A parsing expression.
+///
+/// Patterns are built with the constructors of this module ([`literal`],
+/// [`literal_nocase`], [`class`], [`unicode`], [`any`], [`concat`], [`or`], [`star`],
+/// [`plus`], [`optional`], [`and`], [`not`], [`non_term`], [`non_term_prec`], [`capture`]
+/// and [`throw`]) and compiled into a [`Program`] with [`Pattern::compile`].
+#[derive(Clone)]
+pub enum Pattern {
+    /// Matches the given sequence of u8.
+    Literal(Vec<u8>),
+    /// Matches one u8 contained in [`NormalSet`].
+    Class(NormalSet),
+    /// Matches any n u8.
+    Any(usize),
+    /// Matches all patterns one after the other.
+    Concat(Vec<Pattern>),
+    /// Ordered choice: matches the first pattern that succeeds.
+    Or(Vec<Pattern>),
+    /// Matches pattern zero or more times.
+    Star(Box<Pattern>),
+    /// Matches pattern one or more times.
+    Plus(Box<Pattern>),
+    /// Matches pattern zero or one time.
+    Optional(Box<Pattern>),
+    /// Succeeds if pattern matches, without consuming any input.
+    And(Box<Pattern>),
+    /// Succeeds if pattern does not match, without consuming any input.
+    Not(Box<Pattern>),
+    /// Reference to a rule of a grammar.
+    NonTerm(String),
+    /// Reference to a left-recursive rule of a grammar with a precedence, see
+    /// [`crate::grammar::Grammar::left_recursion`].
+    NonTermPrec(String, usize),
+    /// Captures the part of the subject matched by pattern, with an ID.
+    Capture(usize, Box<Pattern>),
+    /// Throws an error label, see [`Instr::Throw`].
+    Throw(usize),
+}
+
+/// Static properties of a [`Pattern`], computed by [`Pattern::analyze`] or, for the
+/// rules of a grammar, by [`crate::grammar::Grammar::analyze`].
+///
+/// Properties are conservative: a pattern whose rule is unknown is assumed to be
+/// nullable, to start with any u8 and to possibly fail anywhere. A thrown label is
+/// assumed to consume u8, as its recovery rule is expected to skip erroneous input.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    /// FIRST set: the u8 the subject can start with when the [`Pattern`] succeeds.
+    /// Every u8 when the [`Pattern`] is nullable.
+    pub first: NormalSet,
+    /// true if the [`Pattern`] can succeed without consuming any u8.
+    pub nullable: bool,
+    /// true if the [`Pattern`] never fails.
+    pub no_fail: bool,
+    /// true if the [`Pattern`] can only fail at its head, while checking its first u8.
+    pub head_fail: bool,
+}
+
+/// Methods for an [`Analysis`]
+impl Analysis {
+    /// [`Analysis`] of a [`Pattern`] matching the empty string.
+    fn empty() -> Self {
+        Self {
+            first: NormalSet::range(0, 255),
+            nullable: true,
+            no_fail: true,
+            head_fail: false,
+        }
+    }
+
+    /// [`Analysis`] of a [`Pattern`] nothing is known about.
+    fn unknown() -> Self {
+        Self {
+            first: NormalSet::range(0, 255),
+            nullable: true,
+            no_fail: false,
+            head_fail: false,
+        }
+    }
+
+    /// [`Analysis`] assuming nothing, the starting point of the analysis of recursive
+    /// rules, refined until it is stable.
+    pub(crate) fn none() -> Self {
+        Self {
+            first: NormalSet::new(&[]),
+            nullable: false,
+            no_fail: false,
+            head_fail: false,
+        }
+    }
+
+    /// [`Analysis`] of a [`Pattern`] checking a single u8 of set, or n u8 when set is full.
+    fn head(first: NormalSet) -> Self {
+        Self {
+            first,
+            nullable: false,
+            no_fail: false,
+            head_fail: true,
+        }
+    }
+
+    /// Returns true if both [`Analysis`] are the same.
+    pub(crate) fn same(&self, other: &Analysis) -> bool {
+        self.first == other.first
+            && self.nullable == other.nullable
+            && self.no_fail == other.no_fail
+            && self.head_fail == other.head_fail
+    }
+}
+
+/// Name of the rules a [`Pattern`] is compiled with, mapped to the [`Instr::Label`] id
+/// and the [`Analysis`] of each rule.
+#[derive(Default)]
+pub(crate) struct Rules {
+    /// [`Instr::Label`] id of each rule.
+    pub(crate) ids: HashMap<String, usize>,
+    /// [`Analysis`] of each rule.
+    pub(crate) analysis: HashMap<String, Analysis>,
+    /// Left-recursive rules, called with [`Instr::LeftCall`].
+    pub(crate) left: HashSet<String>,
+}
+
+/// Methods for [`Rules`]
+impl Rules {
+    /// Returns the [`Instr::Call`] to the rule named name, or the [`Instr::LeftCall`]
+    /// with precedence if it is left-recursive.
+    fn call(&self, name: &str, precedence: usize) -> Result<Instr, Error> {
+        match self.ids.get(name) {
+            Some(label) if self.left.contains(name) => Ok(Instr::LeftCall(*label, precedence)),
+            Some(label) => Ok(Instr::Call(*label)),
+            None => Err(Error::UndefinedNonTerminal(name.to_owned())),
+        }
+    }
+}
+
+/// Errors returned by [`Pattern`] compilation.
+#[derive(Debug)]
+pub enum Error {
+    /// A [`Pattern::NonTerm`] references a rule that does not exist.
+    UndefinedNonTerminal(String),
+    /// A [`Pattern::Star`] or [`Pattern::Plus`] repeats a nullable [`Pattern`], which
+    /// would loop forever.
+    NullableLoop,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UndefinedNonTerminal(name) => write!(f, "undefined non-terminal '{}'", name),
+            Error::NullableLoop => write!(f, "repetition of a pattern matching the empty string"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Returns a [`Pattern`] matching the given sequence of u8.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::literal;
+/// use crate::ripeg::vm::VM;
+/// let p = literal("ab").compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"abc").length, 2);
+/// assert!(!vm.exec(b"ac").matched);
+/// ```
+pub fn literal<S: AsRef<[u8]>>(s: S) -> Pattern {
+    Pattern::Literal(s.as_ref().to_vec())
+}
+
+/// Returns a [`Pattern`] matching the given sequence of u8, ignoring the case of ASCII
+/// letters.
+///
+/// Each letter is compiled to an [`Instr::Set`] of both its cases (see
+/// [`NormalSet::case_fold`]), other u8 to an [`Instr::Char`].
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::literal_nocase;
+/// use crate::ripeg::vm::VM;
+/// let p = literal_nocase("Go!").compile().unwrap();
+/// assert_eq!(p.disassemble(), "   0  Set [Gg]
+///    1  Set [Oo]
+///    2  Char '!'
+///    3  End
+/// ");
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"gO!").length, 3);
+/// assert!(!vm.exec(b"go?").matched);
+/// ```
+pub fn literal_nocase<S: AsRef<[u8]>>(s: S) -> Pattern {
+    Pattern::Concat(
+        s.as_ref()
+            .iter()
+            .map(|b| Pattern::Class(NormalSet::new(&[*b]).case_fold()))
+            .collect(),
+    )
+}
+
+/// Returns a [`Pattern`] matching one u8 contained in a [`NormalSet`].
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::NormalSet;
+/// use crate::ripeg::pattern::class;
+/// use crate::ripeg::vm::VM;
+/// let p = class(NormalSet::DIGIT).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"42").length, 1);
+/// assert!(!vm.exec(b"x").matched);
+/// ```
+pub fn class(set: NormalSet) -> Pattern {
+    Pattern::Class(set)
+}
+
+/// Returns a [`Pattern`] matching one UTF-8 encoded codepoint contained in ranges.
+///
+/// The codepoints are compiled into an automaton of [`Pattern::Class`] checking the u8 of
+/// their encoding (see [`crate::unicode::utf8_sequences`]), encodings starting with
+/// the same u8 being merged so that no backtracking is needed. Ranges can come from
+/// [`crate::unicode::property`] for Unicode general categories and scripts.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{plus, unicode};
+/// use crate::ripeg::unicode::property;
+/// use crate::ripeg::vm::VM;
+/// let p = unicode(&[('à', 'ÿ')]).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec("é".as_bytes()).length, 2);
+/// assert!(!vm.exec(b"e").matched);
+/// let p = plus(unicode(&property("L").unwrap())).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec("Straße Ωμέγα 42".as_bytes()).length, 7);
+/// assert_eq!(vm.exec("日本語!".as_bytes()).length, 9);
+/// assert!(!vm.exec("42".as_bytes()).matched);
+/// ```
+pub fn unicode(ranges: &[(char, char)]) -> Pattern {
+    utf8_automaton(&crate::unicode::utf8_sequences(ranges))
+}
+
+/// Returns a [`Pattern`] matching one of sequences of u8 ranges, sequences starting
+/// with the same range sharing a single check of it.
+fn utf8_automaton(sequences: &[Vec<(u8, u8)>]) -> Pattern {
+    // single u8 sequences are gathered in one class, tried first.
+    let mut single = NormalSet::new(&[]);
+    let mut alternatives = Vec::new();
+    let mut rest = sequences;
+    while let Some(head) = rest.first().and_then(|s| s.first()).copied() {
+        let same = rest.iter().take_while(|s| s.first() == Some(&head)).count();
+        let tails: Vec<Vec<(u8, u8)>> = rest[..same]
+            .iter()
+            .filter(|s| s.len() > 1)
+            .map(|s| s[1..].to_vec())
+            .collect();
+        if tails.is_empty() {
+            single.add(NormalSet::range(head.0, head.1));
+        } else {
+            alternatives.push(Pattern::Concat(vec![
+                Pattern::Class(NormalSet::range(head.0, head.1)),
+                utf8_automaton(&tails),
+            ]));
+        }
+        rest = &rest[same..];
+    }
+    if single.size() > 0 {
+        alternatives.insert(0, Pattern::Class(single));
+    }
+    match alternatives.len() {
+        1 => alternatives.pop().unwrap(),
+        _ => Pattern::Or(alternatives),
+    }
+}
+
+/// Returns a [`Pattern`] matching any n u8.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::any;
+/// use crate::ripeg::vm::VM;
+/// let p = any(3).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"abcd").length, 3);
+/// assert!(!vm.exec(b"ab").matched);
+/// ```
+pub fn any(n: usize) -> Pattern {
+    Pattern::Any(n)
+}
+
+/// Returns a [`Pattern`] matching all patterns one after the other.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{any, concat, literal};
+/// use crate::ripeg::vm::VM;
+/// let p = concat(vec![literal("a"), any(1), literal("c")]).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"abc").length, 3);
+/// assert!(!vm.exec(b"abd").matched);
+/// ```
+pub fn concat(patterns: Vec<Pattern>) -> Pattern {
+    Pattern::Concat(patterns)
+}
+
+/// Returns a [`Pattern`] matching the first of patterns which succeeds (ordered choice).
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{literal, or};
+/// use crate::ripeg::vm::VM;
+/// let p = or(vec![literal("ab"), literal("a"), literal("b")]).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"abc").length, 2);
+/// assert_eq!(vm.exec(b"ac").length, 1);
+/// assert_eq!(vm.exec(b"b").length, 1);
+/// assert!(!vm.exec(b"c").matched);
+/// ```
+pub fn or(patterns: Vec<Pattern>) -> Pattern {
+    Pattern::Or(patterns)
+}
+
+/// Returns a [`Pattern`] matching pattern zero or more times.
+///
+/// pattern must not match the empty string, otherwise the compiled [`Program`] loops
+/// forever.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::NormalSet;
+/// use crate::ripeg::pattern::{class, literal, star};
+/// use crate::ripeg::vm::VM;
+/// let p = star(literal("ab")).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"ababa").length, 4);
+/// assert_eq!(vm.exec(b"ba").length, 0);
+/// let p = star(class(NormalSet::range(b'a', b'z'))).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"abc0").length, 3);
+/// ```
+pub fn star(pattern: Pattern) -> Pattern {
+    Pattern::Star(Box::new(pattern))
+}
+
+/// Returns a [`Pattern`] matching pattern one or more times.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{literal, plus};
+/// use crate::ripeg::vm::VM;
+/// let p = plus(literal("ab")).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"ababa").length, 4);
+/// assert!(!vm.exec(b"ba").matched);
+/// ```
+pub fn plus(pattern: Pattern) -> Pattern {
+    Pattern::Plus(Box::new(pattern))
+}
+
+/// Returns a [`Pattern`] matching pattern zero or one time.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{literal, optional};
+/// use crate::ripeg::vm::VM;
+/// let p = optional(literal("ab")).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"abab").length, 2);
+/// assert_eq!(vm.exec(b"aa").length, 0);
+/// ```
+pub fn optional(pattern: Pattern) -> Pattern {
+    Pattern::Optional(Box::new(pattern))
+}
+
+/// Returns a [`Pattern`] succeeding if pattern matches, without consuming any input.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{and, any, concat, literal};
+/// use crate::ripeg::vm::VM;
+/// let p = concat(vec![and(literal("a")), any(2)]).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"ab").length, 2);
+/// assert!(!vm.exec(b"ba").matched);
+/// ```
+pub fn and(pattern: Pattern) -> Pattern {
+    Pattern::And(Box::new(pattern))
+}
+
+/// Returns a [`Pattern`] succeeding if pattern does not match, without consuming any input.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{any, concat, literal, not};
+/// use crate::ripeg::vm::VM;
+/// let p = concat(vec![not(literal("a")), any(2)]).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert!(!vm.exec(b"ab").matched);
+/// assert_eq!(vm.exec(b"ba").length, 2);
+/// ```
+pub fn not(pattern: Pattern) -> Pattern {
+    Pattern::Not(Box::new(pattern))
+}
+
+/// Returns a [`Pattern`] referencing the rule named name of a grammar.
+///
+/// A non-terminal can only be compiled as part of a grammar.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{non_term, Error};
+/// assert!(matches!(non_term("Expr").compile(), Err(Error::UndefinedNonTerminal(_))));
+/// ```
+pub fn non_term(name: &str) -> Pattern {
+    Pattern::NonTerm(name.to_owned())
+}
+
+/// Returns a [`Pattern`] referencing the left-recursive rule named name with a
+/// precedence, [`non_term`] having precedence 1.
+///
+/// When growing the match of a left-recursive rule (see
+/// [`crate::grammar::Grammar::left_recursion`]), a left-recursive reference with a lower
+/// precedence fails, and one with a greater precedence grows a match of its own. This
+/// allows operators of different precedences in a single left-recursive rule. The
+/// precedence is ignored when referencing other rules.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::NormalSet;
+/// use crate::ripeg::grammar::Grammar;
+/// use crate::ripeg::pattern::{capture, class, concat, literal, non_term_prec, or};
+/// use crate::ripeg::vm::VM;
+/// // E <- {E^1 '+' E^2} / {E^2 '*' E^3} / [0-9]
+/// let mut g = Grammar::new("E");
+/// g.add("E", or(vec![
+///     capture(1, concat(vec![non_term_prec("E", 1), literal("+"), non_term_prec("E", 2)])),
+///     capture(2, concat(vec![non_term_prec("E", 2), literal("*"), non_term_prec("E", 3)])),
+///     class(NormalSet::range(b'0', b'9')),
+/// ]));
+/// g.left_recursion(true);
+/// let p = g.compile().unwrap();
+/// let m = VM::new(&p).exec(b"1+2*3+4");
+/// assert_eq!(m.length, 7);
+/// // ((1+(2*3))+4)
+/// let sum = &m.captures[0];
+/// assert_eq!((sum.id, sum.start, sum.end), (1, 0, 7));
+/// let inner = &sum.children[0];
+/// assert_eq!((inner.id, inner.start, inner.end), (1, 0, 5));
+/// let product = &inner.children[0];
+/// assert_eq!((product.id, product.start, product.end), (2, 2, 5));
+/// ```
+pub fn non_term_prec(name: &str, precedence: usize) -> Pattern {
+    Pattern::NonTermPrec(name.to_owned(), precedence)
+}
+
+/// Returns a [`Pattern`] capturing the part of the subject matched by pattern.
+///
+/// The capture is returned as a [`crate::capture::Node`] whose ID is id.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::NormalSet;
+/// use crate::ripeg::pattern::{capture, class, concat, literal, plus, star};
+/// use crate::ripeg::vm::VM;
+/// // {0: {1: [0-9]+} (',' {1: [0-9]+})*}
+/// let number = capture(1, plus(class(NormalSet::range(b'0', b'9'))));
+/// let list = concat(vec![number.clone(), star(concat(vec![literal(","), number]))]);
+/// let p = capture(0, list).compile().unwrap();
+/// let subject = b"12,345,6";
+/// let m = VM::new(&p).exec(subject);
+/// assert_eq!(m.captures.len(), 1);
+/// let root = &m.captures[0];
+/// assert_eq!((root.id, root.start, root.end), (0, 0, 8));
+/// let numbers: Vec<&[u8]> = root.children.iter().map(|n| n.text(subject)).collect();
+/// assert_eq!(numbers, vec![&b"12"[..], b"345", b"6"]);
+/// ```
+pub fn capture(id: usize, pattern: Pattern) -> Pattern {
+    Pattern::Capture(id, Box::new(pattern))
+}
+
+/// Returns a [`Pattern`] throwing error label label.
+///
+/// Unlike a failure, a thrown label cannot be caught by an ordered choice: the subject
+/// is rejected unless a recovery rule has been attached to the label with
+/// [`crate::grammar::Grammar::recover`]. Thrown labels are reported by
+/// [`crate::vm::Match::errors`].
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::pattern::{literal, or, throw};
+/// use crate::ripeg::vm::VM;
+/// // 'a' / %{3} / 'b'
+/// let p = or(vec![literal("a"), throw(3), literal("b")]).compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert!(vm.exec(b"a").matched);
+/// let m = vm.exec(b"b");
+/// assert!(!m.matched);
+/// assert_eq!(m.failure.unwrap().label, Some(3));
+/// assert_eq!((m.errors[0].label, m.errors[0].offset), (3, 0));
+/// ```
+pub fn throw(label: usize) -> Pattern {
+    Pattern::Throw(label)
+}
+
+/// Methods for a [`Pattern`]
+impl Pattern {
+    /// Computes the static properties of a [`Pattern`], see [`Analysis`].
+    ///
+    /// Non-terminals are unknown, see [`crate::grammar::Grammar::analyze`] for rules.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::{NormalSet, Set};
+    /// use crate::ripeg::pattern::{class, concat, literal, or, star};
+    /// // [a-z] [0-9]* / 'xy'
+    /// let digits = star(class(NormalSet::range(b'0', b'9')));
+    /// let p = or(vec![concat(vec![class(NormalSet::range(b'a', b'z')), digits]), literal("xy")]);
+    /// let a = p.analyze();
+    /// assert_eq!(a.first.size(), 26);
+    /// assert!(!a.nullable);
+    /// assert!(!a.no_fail);
+    /// assert!(!a.head_fail); // 'xy' can fail at 'y'
+    /// assert!(or(vec![literal("x"), literal("y")]).analyze().head_fail);
+    /// assert!(star(literal("xy")).analyze().no_fail);
+    /// ```
+    pub fn analyze(&self) -> Analysis {
+        self.analyze_with(&HashMap::new())
+    }
+
+    /// Computes the static properties of a [`Pattern`], rules mapping non-terminal names
+    /// to the [`Analysis`] of their rule.
+    pub(crate) fn analyze_with(&self, rules: &HashMap<String, Analysis>) -> Analysis {
+        match self {
+            Pattern::Literal(bytes) => match bytes.first() {
+                Some(b) => Analysis {
+                    head_fail: bytes.len() == 1,
+                    ..Analysis::head(NormalSet::new(&[*b]))
+                },
+                None => Analysis::empty(),
+            },
+            Pattern::Class(set) => Analysis::head(*set),
+            Pattern::Any(0) => Analysis::empty(),
+            Pattern::Any(_) => Analysis::head(NormalSet::range(0, 255)),
+            Pattern::Concat(patterns) => {
+                let analysis: Vec<Analysis> =
+                    patterns.iter().map(|p| p.analyze_with(rules)).collect();
+                match analysis.split_first() {
+                    // a nullable head has a full FIRST set already.
+                    Some((head, tail)) => Analysis {
+                        first: head.first,
+                        nullable: analysis.iter().all(|a| a.nullable),
+                        no_fail: analysis.iter().all(|a| a.no_fail),
+                        head_fail: head.head_fail && tail.iter().all(|a| a.no_fail),
+                    },
+                    None => Analysis::empty(),
+                }
+            }
+            Pattern::Or(patterns) => {
+                let mut analysis = Analysis {
+                    head_fail: true,
+                    ..Analysis::none()
+                };
+                for a in patterns.iter().map(|p| p.analyze_with(rules)) {
+                    analysis.first.add(a.first);
+                    analysis.nullable |= a.nullable;
+                    analysis.no_fail |= a.no_fail;
+                    analysis.head_fail &= a.head_fail;
+                }
+                analysis
+            }
+            Pattern::Star(_) | Pattern::Optional(_) => Analysis::empty(),
+            Pattern::Plus(p) | Pattern::Capture(_, p) => p.analyze_with(rules),
+            Pattern::And(p) => {
+                let a = p.analyze_with(rules);
+                Analysis {
+                    no_fail: a.no_fail,
+                    head_fail: a.head_fail,
+                    ..Analysis::unknown()
+                }
+            }
+            Pattern::Not(_) => Analysis::unknown(),
+            Pattern::Throw(_) => Analysis {
+                nullable: false,
+                ..Analysis::unknown()
+            },
+            Pattern::NonTerm(name) | Pattern::NonTermPrec(name, _) => {
+                rules.get(name).cloned().unwrap_or_else(Analysis::unknown)
+            }
+        }
+    }
+
+    /// Appends to calls the names of the rules a [`Pattern`] can call before consuming
+    /// any u8, rules mapping non-terminal names to the [`Analysis`] of their rule.
+    pub(crate) fn left_calls(&self, rules: &HashMap<String, Analysis>, calls: &mut Vec<String>) {
+        match self {
+            Pattern::Concat(patterns) => {
+                for p in patterns {
+                    p.left_calls(rules, calls);
+                    if !p.analyze_with(rules).nullable {
+                        break;
+                    }
+                }
+            }
+            Pattern::Or(patterns) => {
+                for p in patterns {
+                    p.left_calls(rules, calls);
+                }
+            }
+            Pattern::Star(p)
+            | Pattern::Plus(p)
+            | Pattern::Optional(p)
+            | Pattern::And(p)
+            | Pattern::Not(p)
+            | Pattern::Capture(_, p) => p.left_calls(rules, calls),
+            Pattern::NonTerm(name) | Pattern::NonTermPrec(name, _) => calls.push(name.clone()),
+            Pattern::Literal(_) | Pattern::Class(_) | Pattern::Any(_) | Pattern::Throw(_) => {}
+        }
+    }
+
+    /// Returns true if a [`Pattern`] repeats a nullable pattern, which would loop forever,
+    /// rules mapping non-terminal names to the [`Analysis`] of their rule.
+    pub(crate) fn nullable_loop(&self, rules: &HashMap<String, Analysis>) -> bool {
+        match self {
+            Pattern::Concat(patterns) | Pattern::Or(patterns) => {
+                patterns.iter().any(|p| p.nullable_loop(rules))
+            }
+            Pattern::Star(p) | Pattern::Plus(p) => {
+                p.analyze_with(rules).nullable || p.nullable_loop(rules)
+            }
+            Pattern::Optional(p) | Pattern::And(p) | Pattern::Not(p) | Pattern::Capture(_, p) => {
+                p.nullable_loop(rules)
+            }
+            Pattern::Literal(_)
+            | Pattern::Class(_)
+            | Pattern::Any(_)
+            | Pattern::NonTerm(_)
+            | Pattern::NonTermPrec(..)
+            | Pattern::Throw(_) => false,
+        }
+    }
+
+    /// Compiles a [`Pattern`] into a [`Program`] ending with [`Instr::End`], optimized
+    /// with [`Program::optimize`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::pattern::{class, concat, literal, or, star};
+    /// use crate::ripeg::vm::VM;
+    /// // [a-z]+ ('=' / ':')
+    /// let word = class(NormalSet::range(b'a', b'z'));
+    /// let p = concat(vec![word.clone(), star(word), or(vec![literal("="), literal(":")])]);
+    /// let p = p.compile().unwrap();
+    /// let mut vm = VM::new(&p);
+    /// assert_eq!(vm.exec(b"key=value").length, 4);
+    /// assert_eq!(vm.exec(b"k:v").length, 2);
+    /// assert!(!vm.exec(b"key value").matched);
+    /// ```
+    ///
+    /// Choices whose alternatives start differently need no backtrack entry, see
+    /// [`Pattern::analyze`]:
+    /// ```
+    /// use crate::ripeg::pattern::{literal, or};
+    /// let p = or(vec![literal("ab"), literal("c")]).compile().unwrap();
+    /// assert_eq!(p.disassemble(), "   0  TestCharNoChoice 'a' 3
+    ///    1  Char 'b'
+    ///    2  End
+    ///    3  Char 'c'
+    ///    4  End
+    /// ");
+    /// ```
+    ///
+    /// Repeating a nullable [`Pattern`] would loop forever:
+    /// ```
+    /// use crate::ripeg::pattern::{literal, optional, star, Error};
+    /// let p = star(optional(literal("a")));
+    /// assert!(matches!(p.compile(), Err(Error::NullableLoop)));
+    /// ```
+    pub fn compile(&self) -> Result<Program, Error> {
+        let mut code = Vec::new();
+        self.emit(&mut code, &Rules::default())?;
+        if self.nullable_loop(&HashMap::new()) {
+            return Err(Error::NullableLoop);
+        }
+        code.push(Instr::End);
+        let mut program = Program::from(code);
+        program.optimize();
+        Ok(program)
+    }
+
+    /// Appends the instructions of a [`Pattern`] to code.
+    ///
+    /// Jump targets are absolute indexes in code. rules maps non-terminal names to the
+    /// [`Instr::Label`] id of their rule: [`Pattern::NonTerm`] emits an [`Instr::Call`]
+    /// whose argument is that id, to be resolved once all rules are emitted, or an
+    /// [`Instr::LeftCall`] if the rule is left-recursive.
+    ///
+    /// When the [`Analysis`] of a [`Pattern`] shows that no backtrack entry is needed,
+    /// [`Instr::TestCharNoChoice`] or [`Instr::TestSetNoChoice`] check its head instead
+    /// of [`Instr::Choice`]: when it can only fail at its head, or when the alternative
+    /// cannot start with the same u8.
+    pub(crate) fn emit(&self, code: &mut Vec<Instr>, rules: &Rules) -> Result<(), Error> {
+        match self {
+            Pattern::Literal(bytes) => code.extend(bytes.iter().map(|b| Instr::Char(*b))),
+            Pattern::Class(set) => code.push(Instr::Set(*set)),
+            Pattern::Any(0) => {}
+            Pattern::Any(n) => code.push(Instr::Any(*n)),
+            Pattern::Concat(patterns) => {
+                for p in patterns {
+                    p.emit(code, rules)?;
+                }
+            }
+            Pattern::Or(patterns) => {
+                // p1 / p2 / p3 is compiled as p1 / (p2 / p3)
+                let (last, first) = match patterns.split_last() {
+                    Some(split) => split,
+                    None => {
+                        code.push(Instr::Fail);
+                        return Ok(());
+                    }
+                };
+                // FIRST set of the alternative of each pattern.
+                let mut alternatives = vec![NormalSet::new(&[]); patterns.len()];
+                for k in (0..first.len()).rev() {
+                    alternatives[k] =
+                        alternatives[k + 1] | patterns[k + 1].analyze_with(&rules.analysis).first;
+                }
+                let mut commits = Vec::new();
+                for (p, alternative) in first.iter().zip(&alternatives) {
+                    let choice = match p.head_no_choice(&rules.analysis, alternative) {
+                        Some(test) => {
+                            let test_at = code.len();
+                            code.push(test);
+                            p.emit_tail(code, rules)?;
+                            commits.push(code.len());
+                            code.push(Instr::Jump(0));
+                            test_at
+                        }
+                        None => {
+                            let choice = p.emit_choice(code, rules)?;
+                            commits.push(code.len());
+                            code.push(Instr::Commit(0));
+                            choice
+                        }
+                    };
+                    let at = code.len();
+                    patch(code, choice, at);
+                }
+                last.emit(code, rules)?;
+                for c in commits {
+                    let at = code.len();
+                    patch(code, c, at);
+                }
+            }
+            Pattern::Star(p) => p.emit_star(code, rules)?,
+            Pattern::Plus(p) => {
+                p.emit(code, rules)?;
+                p.emit_star(code, rules)?;
+            }
+            Pattern::Optional(p) => {
+                match p.head_no_choice(&rules.analysis, &NormalSet::range(0, 255)) {
+                    Some(test) => {
+                        let test_at = code.len();
+                        code.push(test);
+                        p.emit_tail(code, rules)?;
+                        let at = code.len();
+                        patch(code, test_at, at);
+                    }
+                    None => {
+                        let choice = p.emit_choice(code, rules)?;
+                        code.push(Instr::Commit(code.len() + 1));
+                        let at = code.len();
+                        patch(code, choice, at);
+                    }
+                }
+            }
+            Pattern::And(p) => {
+                let choice = code.len();
+                code.push(Instr::Choice(0));
+                p.emit(code, rules)?;
+                code.push(Instr::BackCommit(code.len() + 2));
+                let at = code.len();
+                patch(code, choice, at);
+                code.push(Instr::Fail);
+            }
+            Pattern::Not(p) => match p.head_no_choice(&rules.analysis, &NormalSet::range(0, 255)) {
+                // p cannot fail once its head matched: the predicate fails.
+                Some(test) => {
+                    let test_at = code.len();
+                    code.push(test);
+                    p.emit_tail(code, rules)?;
+                    code.push(Instr::Fail);
+                    let at = code.len();
+                    patch(code, test_at, at);
+                }
+                None => {
+                    let choice = code.len();
+                    code.push(Instr::Choice(0));
+                    p.emit(code, rules)?;
+                    code.push(Instr::FailTwice);
+                    let at = code.len();
+                    patch(code, choice, at);
+                }
+            },
+            Pattern::Capture(id, p) => {
+                code.push(Instr::CaptureBegin(*id));
+                p.emit(code, rules)?;
+                code.push(Instr::CaptureEnd);
+            }
+            Pattern::Throw(label) => code.push(Instr::Throw(*label)),
+            Pattern::NonTerm(name) => code.push(rules.call(name, 1)?),
+            Pattern::NonTermPrec(name, precedence) => code.push(rules.call(name, *precedence)?),
+        }
+        Ok(())
+    }
+
+    /// Emits a [`Pattern`] repeated zero or more times.
+    fn emit_star(&self, code: &mut Vec<Instr>, rules: &Rules) -> Result<(), Error> {
+        match self {
+            Pattern::Class(set) => code.push(Instr::Span(*set)),
+            _ => match self.head_no_choice(&rules.analysis, &NormalSet::range(0, 255)) {
+                Some(test) => {
+                    let start = code.len();
+                    code.push(test);
+                    self.emit_tail(code, rules)?;
+                    code.push(Instr::Jump(start));
+                    let at = code.len();
+                    patch(code, start, at);
+                }
+                None => {
+                    let start = code.len();
+                    code.push(Instr::Choice(0));
+                    self.emit(code, rules)?;
+                    code.push(Instr::Commit(start));
+                    let at = code.len();
+                    patch(code, start, at);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Emits a backtrack entry push followed by the [`Pattern`], and returns the index of
+    /// the instruction whose target must be patched with the alternative.
+    ///
+    /// When the [`Pattern`] starts with a u8, a [`NormalSet`] or any n u8, [`Instr::TestChar`],
+    /// [`Instr::TestSet`] or [`Instr::TestAny`] both check it and push the entry, otherwise
+    /// [`Instr::Choice`] is used.
+    fn emit_choice(&self, code: &mut Vec<Instr>, rules: &Rules) -> Result<usize, Error> {
+        let at = code.len();
+        match self.head() {
+            Some(head) => {
+                code.push(head);
+                self.emit_tail(code, rules)?;
+            }
+            None => {
+                code.push(Instr::Choice(0));
+                self.emit(code, rules)?;
+            }
+        }
+        Ok(at)
+    }
+
+    /// Returns the [`Instr::TestCharNoChoice`] or [`Instr::TestSetNoChoice`] checking the
+    /// head of a [`Pattern`], if no backtrack entry is needed once it matched: either the
+    /// [`Pattern`] can only fail at its head, or its alternative, whose FIRST set is
+    /// alternative, cannot start with the same u8.
+    fn head_no_choice(
+        &self,
+        rules: &HashMap<String, Analysis>,
+        alternative: &NormalSet,
+    ) -> Option<Instr> {
+        let head = match self.head()? {
+            Instr::TestChar(c, l) => Instr::TestCharNoChoice(c, l),
+            Instr::TestSet(s, l) => Instr::TestSetNoChoice(s, l),
+            _ => return None,
+        };
+        let analysis = self.analyze_with(rules);
+        match analysis.head_fail || analysis.first.is_disjoint(alternative) {
+            true => Some(head),
+            false => None,
+        }
+    }
+
+    /// Returns the [`Instr::TestChar`], [`Instr::TestSet`] or [`Instr::TestAny`] checking
+    /// the first u8 matched by a [`Pattern`], if it has such a simple head.
+    fn head(&self) -> Option<Instr> {
+        match self {
+            Pattern::Literal(bytes) => bytes.first().map(|b| Instr::TestChar(*b, 0)),
+            Pattern::Class(set) => Some(Instr::TestSet(*set, 0)),
+            Pattern::Any(0) => None,
+            Pattern::Any(n) => Some(Instr::TestAny(*n, 0)),
+            Pattern::Plus(p) => p.head(),
+            Pattern::Concat(patterns) => patterns.first().and_then(Pattern::head),
+            _ => None,
+        }
+    }
+
+    /// Emits a [`Pattern`] without the head checked by [`Pattern::head`].
+    fn emit_tail(&self, code: &mut Vec<Instr>, rules: &Rules) -> Result<(), Error> {
+        match self {
+            Pattern::Literal(bytes) => code.extend(bytes[1..].iter().map(|b| Instr::Char(*b))),
+            Pattern::Class(_) | Pattern::Any(_) => {}
+            Pattern::Plus(p) => {
+                p.emit_tail(code, rules)?;
+                p.emit_star(code, rules)?;
+            }
+            Pattern::Concat(patterns) => {
+                patterns[0].emit_tail(code, rules)?;
+                for p in &patterns[1..] {
+                    p.emit(code, rules)?;
+                }
+            }
+            _ => self.emit(code, rules)?,
+        }
+        Ok(())
+    }
+}
+
+/// Sets the jump target of the instruction at index at.
+fn patch(code: &mut [Instr], at: usize, target: usize) {
+    match &mut code[at] {
+        Instr::BackCommit(l)
+        | Instr::Call(l)
+        | Instr::Choice(l)
+        | Instr::Commit(l)
+        | Instr::Jump(l)
+        | Instr::LeftCall(l, _)
+        | Instr::MemoOpen(l, _)
+        | Instr::PartialCommit(l)
+        | Instr::TestAny(_, l)
+        | Instr::TestChar(_, l)
+        | Instr::TestCharNoChoice(_, l)
+        | Instr::TestSet(_, l)
+        | Instr::TestSetNoChoice(_, l) => *l = target,
+        _ => unreachable!("instruction at {} has no jump target", at),
+    }
+}
@@ -0,0 +1,637 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! re module parses grammars written in text, in the spirit of LPeg `re` module.
+//!
+//! ```text
+//! Grammar    <- Spacing Rule+
+//! Rule       <- Name '<-' Expression
+//! Expression <- Sequence ('/' Sequence)*
+//! Sequence   <- Prefix*
+//! Prefix     <- ('&' / '!')? Suffix
+//! Suffix     <- Primary [*+?]*
+//! Primary    <- '(' Expression ')' / Literal / Class / Property / Named / '.' / Throw
+//!               / Name ('^' [0-9]+)? !'<-'
+//! Literal    <- ("'" (!"'" Char)* "'" / '"' (!'"' Char)* '"') NoCase?
+//! Class      <- '[' '^'? (!']' Range)+ ']' NoCase?
+//! NoCase     <- 'i' ![a-zA-Z0-9_]
+//! Range      <- Property / Named / Char '-' Char / Char
+//! Property   <- '\' [pP] '{' [a-zA-Z0-9_ =-]+ '}'
+//! Named      <- '%' [a-zA-Z_] [a-zA-Z0-9_]*
+//! Throw      <- '%{' [0-9]+ '}'
+//! Char       <- '\' [nrt'"%\[\]\-] / '\x' [0-9a-fA-F]{2} / .
+//! Comment    <- '--' (!EndOfLine .)*
+//! ```
+//!
+//! The first rule is the start rule of the [`Grammar`]. `Name^k` references a
+//! left-recursive rule with precedence k, see [`pattern::non_term_prec`].
+//!
+//! `\p{Name}` matches a UTF-8 encoded codepoint of a Unicode general category or script
+//! (see [`unicode::property`]), `\P{Name}` one that is not. A class containing a
+//! property or a non-ASCII character matches a UTF-8 encoded codepoint too, see
+//! [`pattern::unicode`], `\x` escapes then being codepoints; otherwise it matches a u8.
+//!
+//! `%name` matches a u8 of the predefined class name (see [`NormalSet::named`]), or of
+//! one of the abbreviations of LPeg: `%a` alpha, `%c` cntrl, `%d` digit, `%g` graph, `%l`
+//! lower, `%p` punct, `%s` space, `%u` upper, `%w` alnum and `%x` xdigit, the uppercase
+//! letter matching the complement of the class. In a class, `%name` adds the u8 of the
+//! predefined class, which must be ASCII ones in a class of codepoints; `\%` is a `%`.
+//!
+//! A literal or a class followed by `i` ignores case: ASCII case for literals (see
+//! [`pattern::literal_nocase`]) and u8 classes (see [`NormalSet::case_fold`]), Unicode
+//! simple case folding for the other classes (see [`unicode::case_fold`]).
+
+use std::fmt;
+
+use crate::charset::{NormalSet, Set};
+use crate::grammar::Grammar;
+use crate::pattern::{self, Pattern};
+use crate::unicode;
+
+/// Syntax error in a grammar text.
+#[derive(Debug)]
+pub struct Error {
+    /// Line of the error, starting at 1.
+    pub line: usize,
+    /// Column of the error in u8, starting at 1.
+    pub column: usize,
+    /// Description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parses a grammar text into a [`Grammar`], the first rule being the start rule.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// let g = parse("
+///     Expr <- Term ('+' Term)*   -- sums
+///     Term <- [0-9]+ / '(' Expr ')'
+/// ").unwrap();
+/// let p = g.compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"1+(2+30)+4").length, 10);
+/// assert!(!vm.exec(b"+1").matched);
+/// ```
+///
+/// Classes, escapes and predicates:
+/// ```
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// let g = parse(r#"String <- '"' (!["\\] . / '\\' ["\\nt])* '"' ![\x00-\x1f]"#).unwrap();
+/// let p = g.compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(br#""a\"b" c"#).length, 6);
+/// assert!(!vm.exec(b"\"a\"\n").matched);
+/// assert!(!vm.exec(br#""a\qb""#).matched);
+/// ```
+///
+/// Unicode classes and properties:
+/// ```
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// let g = parse(r"Word <- [\p{L}\p{Nd}_]+ / [à-ÿ] / \P{Greek}").unwrap();
+/// let p = g.compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec("Ωμέγα_2 x".as_bytes()).length, 12);
+/// assert_eq!(vm.exec("€".as_bytes()).length, 3);
+/// assert!(!vm.exec("͵".as_bytes()).matched); // Greek lower numeral sign
+/// let e = parse(r"A <- \p{Klingon}").err().unwrap();
+/// assert_eq!(e.to_string(), "1:9: unknown Unicode property");
+/// ```
+///
+/// Case-insensitive literals and classes:
+/// ```
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// let g = parse(r"Select <- 'select'i ' '+ [a-zé]i+").unwrap();
+/// let p = g.compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec("SeLeCt AbÉ".as_bytes()).length, 11);
+/// assert!(!vm.exec(b"selekt a").matched);
+/// ```
+///
+/// Predefined classes:
+/// ```
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// let g = parse(r"Line <- %s* [_%a] [_%w]* ':' %S+ %s %xdigit+ [\%%D]").unwrap();
+/// let p = g.compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"  _id0:#!/ 2f%").length, 14);
+/// assert!(!vm.exec(b"id: x 2f%").matched);
+/// let e = parse("A <- %klingon").err().unwrap();
+/// assert_eq!(e.to_string(), "1:6: unknown class name");
+/// let e = parse("A <- [é%utf8_lead]").err().unwrap();
+/// assert_eq!(e.to_string(), "1:9: non-ASCII named class in a Unicode class");
+/// ```
+///
+/// Left-recursive rules with precedences, see [`Grammar::left_recursion`]:
+/// ```
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// let mut g = parse("
+///     Expr <- Expr^1 [+-] Expr^2 / Expr^2 [*/] Expr^3 / '(' Expr ')' / [0-9]+
+/// ").unwrap();
+/// g.left_recursion(true);
+/// let p = g.compile().unwrap();
+/// let mut vm = VM::new(&p);
+/// assert_eq!(vm.exec(b"1+2*(3-4)/5").length, 11);
+/// assert_eq!(vm.exec(b"1+2*").length, 3);
+/// assert!(parse("A <- A^x").is_err());
+/// ```
+///
+/// Syntax errors report their position:
+/// ```
+/// use crate::ripeg::re::parse;
+/// let e = parse("A <- 'a'\nB <- [a-z").err().unwrap();
+/// assert_eq!((e.line, e.column), (2, 6));
+/// assert_eq!(e.to_string(), "2:6: unterminated class");
+/// ```
+pub fn parse(text: &str) -> Result<Grammar, Error> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+    };
+    parser.spacing();
+    let mut grammar: Option<Grammar> = None;
+    while parser.pos < parser.text.len() {
+        let name = match parser.name() {
+            Some(name) => name,
+            None => return Err(parser.error("expected rule name")),
+        };
+        parser.spacing();
+        if !parser.eat(b"<-") {
+            return Err(parser.error("expected '<-'"));
+        }
+        parser.spacing();
+        let p = parser.expression()?;
+        grammar
+            .get_or_insert_with(|| Grammar::new(&name))
+            .add(&name, p);
+    }
+    grammar.ok_or_else(|| parser.error("expected rule name"))
+}
+
+/// Recursive descent parser over a grammar text.
+struct Parser<'a> {
+    /// Grammar text.
+    text: &'a [u8],
+    /// Current position in text.
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns an [`Error`] at the current position.
+    fn error(&self, message: &str) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    /// Returns an [`Error`] at position pos.
+    fn error_at(&self, pos: usize, message: &str) -> Error {
+        let before = &self.text[..pos];
+        let line_start = before
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map_or(0, |i| i + 1);
+        Error {
+            line: before.iter().filter(|c| **c == b'\n').count() + 1,
+            column: pos - line_start + 1,
+            message: message.to_owned(),
+        }
+    }
+
+    /// Returns the u8 at the current position, if any.
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    /// Consumes s if the text continues with it.
+    fn eat(&mut self, s: &[u8]) -> bool {
+        if self.text[self.pos..].starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips white spaces and comments.
+    fn spacing(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => self.pos += 1,
+                Some(b'-') if self.text[self.pos..].starts_with(b"--") => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Parses a rule name, without skipping the spaces following it.
+    fn name(&mut self) -> Option<String> {
+        let start = self.pos;
+        if let Some(b'a'..=b'z' | b'A'..=b'Z' | b'_') = self.peek() {
+            self.pos += 1;
+            while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') = self.peek() {
+                self.pos += 1;
+            }
+            Some(String::from_utf8_lossy(&self.text[start..self.pos]).into_owned())
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if a rule definition starts at the current position.
+    fn at_rule(&mut self) -> bool {
+        let start = self.pos;
+        let found = self.name().is_some() && {
+            self.spacing();
+            self.eat(b"<-")
+        };
+        self.pos = start;
+        found
+    }
+
+    /// Expression <- Sequence ('/' Sequence)*
+    fn expression(&mut self) -> Result<Pattern, Error> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat(b"/") {
+            self.spacing();
+            alternatives.push(self.sequence()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => pattern::or(alternatives),
+        })
+    }
+
+    /// Sequence <- Prefix*
+    fn sequence(&mut self) -> Result<Pattern, Error> {
+        let mut items = Vec::new();
+        while let Some(p) = self.prefix()? {
+            items.push(p);
+        }
+        Ok(match items.len() {
+            1 => items.pop().unwrap(),
+            _ => pattern::concat(items),
+        })
+    }
+
+    /// Prefix <- ('&' / '!')? Suffix
+    fn prefix(&mut self) -> Result<Option<Pattern>, Error> {
+        let build: fn(Pattern) -> Pattern = match self.peek() {
+            Some(b'&') => pattern::and,
+            Some(b'!') => pattern::not,
+            _ => return self.suffix(),
+        };
+        self.pos += 1;
+        self.spacing();
+        match self.prefix()? {
+            Some(p) => Ok(Some(build(p))),
+            None => Err(self.error("expected expression")),
+        }
+    }
+
+    /// Suffix <- Primary [*+?]*
+    fn suffix(&mut self) -> Result<Option<Pattern>, Error> {
+        let mut p = match self.primary()? {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        loop {
+            p = match self.peek() {
+                Some(b'*') => pattern::star(p),
+                Some(b'+') => pattern::plus(p),
+                Some(b'?') => pattern::optional(p),
+                _ => return Ok(Some(p)),
+            };
+            self.pos += 1;
+            self.spacing();
+        }
+    }
+
+    /// Primary <- '(' Expression ')' / Literal / Class / Property / Named / '.' / Throw
+    ///            / Name ('^' [0-9]+)? !'<-'
+    fn primary(&mut self) -> Result<Option<Pattern>, Error> {
+        let p = match self.peek() {
+            Some(b'(') => {
+                let start = self.pos;
+                self.pos += 1;
+                self.spacing();
+                let p = self.expression()?;
+                if !self.eat(b")") {
+                    return Err(self.error_at(start, "unclosed parenthesis"));
+                }
+                p
+            }
+            Some(q @ (b'\'' | b'"')) => self.literal(q)?,
+            Some(b'[') => self.class()?,
+            Some(b'\\') => pattern::unicode(&self.property()?),
+            Some(b'.') => {
+                self.pos += 1;
+                pattern::any(1)
+            }
+            Some(b'%') if self.text.get(self.pos + 1) == Some(&b'{') => self.throw()?,
+            Some(b'%') => pattern::class(self.named()?),
+            Some(b'a'..=b'z' | b'A'..=b'Z' | b'_') if !self.at_rule() => {
+                let name = self.name().unwrap();
+                match self.eat(b"^") {
+                    true => pattern::non_term_prec(&name, self.precedence()?),
+                    false => pattern::non_term(&name),
+                }
+            }
+            _ => return Ok(None),
+        };
+        self.spacing();
+        Ok(Some(p))
+    }
+
+    /// Parses the [0-9]+ precedence following '^'.
+    fn precedence(&mut self) -> Result<usize, Error> {
+        let digits = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[digits..self.pos])
+            .unwrap()
+            .parse()
+            .map_err(|_| self.error_at(digits, "invalid precedence"))
+    }
+
+    /// Named <- '%' [a-zA-Z_] [a-zA-Z0-9_]*
+    fn named(&mut self) -> Result<NormalSet, Error> {
+        let start = self.pos;
+        self.pos += 1;
+        let name = self.pos;
+        while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') = self.peek() {
+            self.pos += 1;
+        }
+        let name = std::str::from_utf8(&self.text[name..self.pos]).unwrap();
+        let set = match *name.as_bytes() {
+            [c] => match c.to_ascii_lowercase() {
+                b'a' => Some(NormalSet::ALPHA),
+                b'c' => Some(NormalSet::CNTRL),
+                b'd' => Some(NormalSet::DIGIT),
+                b'g' => Some(NormalSet::GRAPH),
+                b'l' => Some(NormalSet::LOWER),
+                b'p' => Some(NormalSet::PUNCT),
+                b's' => Some(NormalSet::SPACE),
+                b'u' => Some(NormalSet::UPPER),
+                b'w' => Some(NormalSet::ALNUM),
+                b'x' => Some(NormalSet::XDIGIT),
+                _ => None,
+            }
+            .map(|set| if c.is_ascii_uppercase() { !set } else { set }),
+            _ => NormalSet::named(name),
+        };
+        set.ok_or_else(|| self.error_at(start, "unknown class name"))
+    }
+
+    /// Throw <- '%{' [0-9]+ '}'
+    fn throw(&mut self) -> Result<Pattern, Error> {
+        let start = self.pos;
+        if !self.eat(b"%{") {
+            return Err(self.error("expected '{' after '%'"));
+        }
+        let digits = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        let label = std::str::from_utf8(&self.text[digits..self.pos])
+            .unwrap()
+            .parse()
+            .map_err(|_| self.error_at(digits, "invalid error label"))?;
+        if !self.eat(b"}") {
+            return Err(self.error_at(start, "unclosed error label"));
+        }
+        Ok(pattern::throw(label))
+    }
+
+    /// Literal <- "'" (!"'" Char)* "'" / '"' (!'"' Char)* '"'
+    fn literal(&mut self, quote: u8) -> Result<Pattern, Error> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(b'\n') => return Err(self.error_at(start, "unterminated literal")),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(match self.nocase() {
+                        true => pattern::literal_nocase(bytes),
+                        false => pattern::literal(bytes),
+                    });
+                }
+                Some(_) => bytes.push(self.char()?),
+            }
+        }
+    }
+
+    /// Class <- '[' '^'? (!']' Range)+ ']'
+    fn class(&mut self) -> Result<Pattern, Error> {
+        let start = self.pos;
+        self.pos += 1;
+        let negated = self.eat(b"^");
+        let mut ranges = Vec::new();
+        let mut empty = true;
+        let mut utf8 = false;
+        let mut non_ascii = None;
+        loop {
+            match self.peek() {
+                None | Some(b'\n') => return Err(self.error_at(start, "unterminated class")),
+                Some(b']') if !empty => break,
+                Some(b']') => return Err(self.error("empty class")),
+                Some(b'\\') if matches!(self.text.get(self.pos + 1), Some(b'p' | b'P')) => {
+                    ranges.extend(self.property()?);
+                    utf8 = true;
+                }
+                Some(b'%')
+                    if matches!(
+                        self.text.get(self.pos + 1),
+                        Some(b'a'..=b'z' | b'A'..=b'Z' | b'_')
+                    ) =>
+                {
+                    let named_pos = self.pos;
+                    let set = self.named()?;
+                    if !set.is_small() {
+                        non_ascii = non_ascii.or(Some(named_pos));
+                    }
+                    for c in set.iter().map(char::from) {
+                        match ranges.last_mut() {
+                            Some((_, high)) if *high as u32 + 1 == c as u32 => *high = c,
+                            _ => ranges.push((c, c)),
+                        }
+                    }
+                }
+                Some(_) => {
+                    let low_pos = self.pos;
+                    let low = self.class_char(&mut utf8)?;
+                    if self.peek() == Some(b'-') && self.text.get(self.pos + 1) != Some(&b']') {
+                        self.pos += 1;
+                        let high = self.class_char(&mut utf8)?;
+                        if high < low {
+                            return Err(self.error_at(low_pos, "invalid class range"));
+                        }
+                        ranges.push((low, high));
+                    } else {
+                        ranges.push((low, low));
+                    }
+                }
+            }
+            empty = false;
+        }
+        self.pos += 1;
+        let nocase = self.nocase();
+        if let (true, Some(named_pos)) = (utf8, non_ascii) {
+            return Err(self.error_at(named_pos, "non-ASCII named class in a Unicode class"));
+        }
+        if utf8 {
+            let ranges = match nocase {
+                true => unicode::case_fold(&ranges),
+                false => ranges,
+            };
+            return Ok(pattern::unicode(&match negated {
+                true => unicode::complement(&ranges),
+                false => ranges,
+            }));
+        }
+        // without non-ASCII characters, every char is a u8.
+        let mut set = NormalSet::new(&[]);
+        for (low, high) in ranges {
+            set.add(NormalSet::range(low as u8, high as u8));
+        }
+        if nocase {
+            set = set.case_fold();
+        }
+        Ok(pattern::class(if negated { set.complement() } else { set }))
+    }
+
+    /// NoCase <- 'i' ![a-zA-Z0-9_]
+    fn nocase(&mut self) -> bool {
+        let flag = self.peek() == Some(b'i')
+            && !matches!(
+                self.text.get(self.pos + 1),
+                Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_')
+            );
+        if flag {
+            self.pos += 1;
+        }
+        flag
+    }
+
+    /// Parses the Char of a Class, setting utf8 if it is a non-ASCII character.
+    fn class_char(&mut self, utf8: &mut bool) -> Result<char, Error> {
+        let length = match self.peek() {
+            Some(0xC0..=0xDF) => 2,
+            Some(0xE0..=0xEF) => 3,
+            Some(0xF0..=0xFF) => 4,
+            _ => return self.char().map(char::from),
+        };
+        let c = self
+            .text
+            .get(self.pos..self.pos + length)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .and_then(|s| s.chars().next())
+            .ok_or_else(|| self.error("invalid UTF-8 character"))?;
+        self.pos += length;
+        *utf8 = true;
+        Ok(c)
+    }
+
+    /// Property <- '\' [pP] '{' [a-zA-Z0-9_ =-]+ '}'
+    ///
+    /// Returns the codepoint ranges of the property, complemented for `\P`.
+    fn property(&mut self) -> Result<Vec<(char, char)>, Error> {
+        let start = self.pos;
+        self.pos += 1;
+        let negated = match self.peek() {
+            Some(b'p') => false,
+            Some(b'P') => true,
+            _ => return Err(self.error_at(start, "invalid escape sequence")),
+        };
+        self.pos += 1;
+        if !self.eat(b"{") {
+            return Err(self.error("expected '{' after Unicode property escape"));
+        }
+        let name = self.pos;
+        while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b' ' | b'=' | b'-') =
+            self.peek()
+        {
+            self.pos += 1;
+        }
+        let end = self.pos;
+        if !self.eat(b"}") {
+            return Err(self.error_at(start, "unclosed Unicode property"));
+        }
+        let ranges = std::str::from_utf8(&self.text[name..end])
+            .ok()
+            .and_then(unicode::property)
+            .ok_or_else(|| self.error_at(name, "unknown Unicode property"))?;
+        Ok(match negated {
+            true => unicode::complement(&ranges),
+            false => ranges,
+        })
+    }
+
+    /// Char <- '\' [nrt'"%\[\]\-] / '\x' [0-9a-fA-F]{2} / .
+    fn char(&mut self) -> Result<u8, Error> {
+        let start = self.pos;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(self.error("unexpected end of text")),
+        };
+        self.pos += 1;
+        if c != b'\\' {
+            return Ok(c);
+        }
+        let e = self.peek();
+        self.pos += 1;
+        match e {
+            Some(b'n') => Ok(b'\n'),
+            Some(b'r') => Ok(b'\r'),
+            Some(b't') => Ok(b'\t'),
+            Some(c @ (b'\'' | b'"' | b'%' | b'\\' | b'[' | b']' | b'-')) => Ok(c),
+            Some(b'x') => {
+                let hex = self.text.get(self.pos..self.pos + 2).unwrap_or_default();
+                match std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                {
+                    Some(c) if hex.iter().all(u8::is_ascii_hexdigit) => {
+                        self.pos += 2;
+                        Ok(c)
+                    }
+                    _ => Err(self.error_at(start, "invalid hexadecimal escape")),
+                }
+            }
+            _ => Err(self.error_at(start, "invalid escape sequence")),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! scan module provides [`Scanner`], which finds the first u8 of a slice not contained in
+//! a [`NormalSet`], as [`crate::isa::Instr::Span`] does.
+//!
+//! On x86_64, 16 or 32 u8 are checked at once with SSE2, SSSE3 or AVX2 instructions,
+//! chosen at runtime according to the CPU. Sets of one to three u8 are checked by
+//! comparing each u8 of the slice to the members, the other sets by looking each u8 up
+//! in the 32 bytes of the set, the byte being selected by the high bits of the u8 and the
+//! bit by its low bits. The first 16 u8 of a slice, where most spans end, and the u8 left
+//! after the last full vector are checked by a scalar loop, as on other architectures.
+
+use crate::charset::{NormalSet, Set};
+
+/// Number of u8 checked one at a time by [`Scanner::span`] before the vector instructions.
+const PREFIX: usize = 16;
+
+/// Finds the first u8 of a slice not contained in a [`NormalSet`], the instructions being
+/// chosen once when the [`Scanner`] is built.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::NormalSet;
+/// use crate::ripeg::scan::Scanner;
+/// let s = Scanner::new(&NormalSet::SPACE);
+/// assert_eq!(s.span(b"  \t\n  x  "), 6);
+/// let utf8 = NormalSet::UTF8_LEAD | NormalSet::UTF8_CONTINUATION;
+/// let s = Scanner::new(&(NormalSet::WORD | utf8));
+/// let text = "identifier_with_a_rather_long_name_déjà_vu + 1";
+/// assert_eq!(s.span(text.as_bytes()), 44);
+/// ```
+#[derive(Clone)]
+pub struct Scanner {
+    /// Set of the u8 spanned.
+    set: NormalSet,
+    /// Members of the set when it has one to three of them, repeated to fill the array.
+    members: Option<[u8; 3]>,
+    /// Implementation of [`Scanner::span`].
+    span: unsafe fn(&Scanner, &[u8]) -> usize,
+}
+
+/// Methods for a [`Scanner`]
+impl Scanner {
+    /// Instanciate a [`Scanner`] spanning the u8 of set
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::scan::Scanner;
+    /// let s = Scanner::new(&NormalSet::from_bytes(b"ab"));
+    /// assert_eq!(s.span(b"abba"), 4);
+    /// assert_eq!(s.span(b"abc"), 2);
+    /// assert_eq!(s.span(b""), 0);
+    /// ```
+    pub fn new(set: &NormalSet) -> Scanner {
+        let members = match set.size() {
+            1..=3 => {
+                let mut iter = set.iter();
+                let first = iter.next().unwrap();
+                let second = iter.next().unwrap_or(first);
+                Some([first, second, iter.next().unwrap_or(second)])
+            }
+            _ => None,
+        };
+        Scanner {
+            set: *set,
+            members,
+            #[cfg(target_arch = "x86_64")]
+            span: x86::select(members.is_some()),
+            #[cfg(not(target_arch = "x86_64"))]
+            span: |scanner, haystack| scanner.span_scalar(haystack, 0),
+        }
+    }
+
+    /// Returns the number of u8 at the start of haystack contained in the set of a
+    /// [`Scanner`], that is the position of the first u8 not in the set, or the length
+    /// of haystack if there is none.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::scan::Scanner;
+    /// let haystack: Vec<u8> = (0..100u8).map(|k| b'0' + k % 10).collect();
+    /// let s = Scanner::new(&NormalSet::DIGIT);
+    /// for end in 0..=haystack.len() {
+    ///     assert_eq!(s.span(&haystack[..end]), end);
+    ///     let mut h = haystack.clone();
+    ///     h.insert(end, b'x');
+    ///     assert_eq!(s.span(&h), end);
+    /// }
+    /// let s = Scanner::new(&NormalSet::range(0x80, 0xff));
+    /// assert_eq!(s.span("ça été aimé".as_bytes()), 2);
+    /// assert_eq!(Scanner::new(&NormalSet::range(0, 255)).span(&haystack), 100);
+    /// assert_eq!(Scanner::new(&NormalSet::from_bytes(b"")).span(&haystack), 0);
+    /// ```
+    #[inline]
+    pub fn span(&self, haystack: &[u8]) -> usize {
+        // most spans are short: their u8 are checked without calling the implementation.
+        let prefix = haystack.len().min(PREFIX);
+        if let Some(n) = haystack[..prefix].iter().position(|&c| !self.set.has(c)) {
+            return n;
+        }
+        if prefix == haystack.len() {
+            return prefix;
+        }
+        // SAFETY: only functions whose target features the CPU has are selected.
+        prefix + unsafe { (self.span)(self, &haystack[prefix..]) }
+    }
+
+    /// Scalar [`Scanner::span`] of haystack from position start.
+    #[inline]
+    fn span_scalar(&self, haystack: &[u8], start: usize) -> usize {
+        let rest = &haystack[start..];
+        start
+            + match self.members {
+                Some([a, b, c]) => rest.iter().position(|&x| x != a && x != b && x != c),
+                None => rest.iter().position(|&x| !self.set.has(x)),
+            }
+            .unwrap_or(rest.len())
+    }
+}
+
+/// x86_64 implementations of [`Scanner::span`].
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::Scanner;
+
+    /// Returns the fastest implementation of [`Scanner::span`] for the CPU, comparing
+    /// the u8 to the members of the set if members is true, looking them up in the
+    /// bytes of the set otherwise.
+    pub(super) fn select(members: bool) -> unsafe fn(&Scanner, &[u8]) -> usize {
+        let avx2 = is_x86_feature_detected!("avx2");
+        match members {
+            true if avx2 => span_members_avx2,
+            true => span_members_sse2,
+            false if avx2 => span_bitmap_avx2,
+            false if is_x86_feature_detected!("ssse3") => span_bitmap_ssse3,
+            false => span_scalar,
+        }
+    }
+
+    /// Scalar implementation, for CPU without SSSE3.
+    unsafe fn span_scalar(scanner: &Scanner, haystack: &[u8]) -> usize {
+        scanner.span_scalar(haystack, 0)
+    }
+
+    /// Compares 16 u8 at once to the members of the set.
+    #[target_feature(enable = "sse2")]
+    unsafe fn span_members_sse2(scanner: &Scanner, haystack: &[u8]) -> usize {
+        let [a, b, c] = scanner.members.unwrap_or_default();
+        let (a, b, c) = (
+            _mm_set1_epi8(a as i8),
+            _mm_set1_epi8(b as i8),
+            _mm_set1_epi8(c as i8),
+        );
+        let mut i = 0;
+        while i + 16 <= haystack.len() {
+            let v = _mm_loadu_si128(haystack.as_ptr().add(i) as *const __m128i);
+            let m = _mm_or_si128(
+                _mm_or_si128(_mm_cmpeq_epi8(v, a), _mm_cmpeq_epi8(v, b)),
+                _mm_cmpeq_epi8(v, c),
+            );
+            let out = !_mm_movemask_epi8(m) as u32 & 0xffff;
+            if out != 0 {
+                return i + out.trailing_zeros() as usize;
+            }
+            i += 16;
+        }
+        scanner.span_scalar(haystack, i)
+    }
+
+    /// Compares 32 u8 at once to the members of the set.
+    #[target_feature(enable = "avx2")]
+    unsafe fn span_members_avx2(scanner: &Scanner, haystack: &[u8]) -> usize {
+        let [a, b, c] = scanner.members.unwrap_or_default();
+        let (a, b, c) = (
+            _mm256_set1_epi8(a as i8),
+            _mm256_set1_epi8(b as i8),
+            _mm256_set1_epi8(c as i8),
+        );
+        let mut i = 0;
+        while i + 32 <= haystack.len() {
+            let v = _mm256_loadu_si256(haystack.as_ptr().add(i) as *const __m256i);
+            let m = _mm256_or_si256(
+                _mm256_or_si256(_mm256_cmpeq_epi8(v, a), _mm256_cmpeq_epi8(v, b)),
+                _mm256_cmpeq_epi8(v, c),
+            );
+            let out = !(_mm256_movemask_epi8(m) as u32);
+            if out != 0 {
+                return i + out.trailing_zeros() as usize;
+            }
+            i += 32;
+        }
+        span_members_sse2(scanner, &haystack[i..]) + i
+    }
+
+    /// Selects bit c % 8 of a byte of the set: 1 << (c % 8).
+    const BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+    /// Returns the bytes 0 to 15 and 16 to 31 of the set: byte c / 8 holds u8 c at
+    /// bit c % 8, the words of the set being little-endian.
+    #[inline]
+    fn bytes(scanner: &Scanner) -> ([u8; 16], [u8; 16]) {
+        let bits = scanner.set.bits;
+        let (mut low, mut high) = ([0u8; 16], [0u8; 16]);
+        low[..8].copy_from_slice(&bits[0].to_le_bytes());
+        low[8..].copy_from_slice(&bits[1].to_le_bytes());
+        high[..8].copy_from_slice(&bits[2].to_le_bytes());
+        high[8..].copy_from_slice(&bits[3].to_le_bytes());
+        (low, high)
+    }
+
+    /// Loads 16 bytes into a 128 bits register.
+    #[inline]
+    unsafe fn load(bytes: &[u8; 16]) -> __m128i {
+        _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+    }
+
+    /// Looks 16 u8 at once up in the bytes of the set.
+    #[target_feature(enable = "ssse3")]
+    unsafe fn span_bitmap_ssse3(scanner: &Scanner, haystack: &[u8]) -> usize {
+        let (low, high) = bytes(scanner);
+        let (low, high, bits) = (load(&low), load(&high), load(&BITS));
+        let (nibble, sign, seven) = (_mm_set1_epi8(0x0f), _mm_set1_epi8(-128), _mm_set1_epi8(7));
+        let mut i = 0;
+        while i + 16 <= haystack.len() {
+            let v = _mm_loadu_si128(haystack.as_ptr().add(i) as *const __m128i);
+            // byte (c / 8) % 16, shuffles giving 0 for indexes whose sign bit is set.
+            let index = _mm_and_si128(_mm_srli_epi16(v, 3), nibble);
+            let byte = _mm_or_si128(
+                _mm_shuffle_epi8(low, _mm_or_si128(index, _mm_and_si128(v, sign))),
+                _mm_shuffle_epi8(high, _mm_or_si128(index, _mm_andnot_si128(v, sign))),
+            );
+            let bit = _mm_shuffle_epi8(bits, _mm_and_si128(v, seven));
+            let m = _mm_and_si128(byte, bit);
+            let out = _mm_movemask_epi8(_mm_cmpeq_epi8(m, _mm_setzero_si128())) as u32;
+            if out != 0 {
+                return i + out.trailing_zeros() as usize;
+            }
+            i += 16;
+        }
+        scanner.span_scalar(haystack, i)
+    }
+
+    /// Looks 32 u8 at once up in the bytes of the set.
+    #[target_feature(enable = "avx2")]
+    unsafe fn span_bitmap_avx2(scanner: &Scanner, haystack: &[u8]) -> usize {
+        let (low, high) = bytes(scanner);
+        let low = _mm256_broadcastsi128_si256(load(&low));
+        let high = _mm256_broadcastsi128_si256(load(&high));
+        let bits = _mm256_broadcastsi128_si256(load(&BITS));
+        let (nibble, sign, seven) = (
+            _mm256_set1_epi8(0x0f),
+            _mm256_set1_epi8(-128),
+            _mm256_set1_epi8(7),
+        );
+        let mut i = 0;
+        while i + 32 <= haystack.len() {
+            let v = _mm256_loadu_si256(haystack.as_ptr().add(i) as *const __m256i);
+            let index = _mm256_and_si256(_mm256_srli_epi16(v, 3), nibble);
+            let byte = _mm256_or_si256(
+                _mm256_shuffle_epi8(low, _mm256_or_si256(index, _mm256_and_si256(v, sign))),
+                _mm256_shuffle_epi8(high, _mm256_or_si256(index, _mm256_andnot_si256(v, sign))),
+            );
+            let bit = _mm256_shuffle_epi8(bits, _mm256_and_si256(v, seven));
+            let m = _mm256_and_si256(byte, bit);
+            let out = _mm256_movemask_epi8(_mm256_cmpeq_epi8(m, _mm256_setzero_si256())) as u32;
+            if out != 0 {
+                return i + out.trailing_zeros() as usize;
+            }
+            i += 32;
+        }
+        span_bitmap_ssse3(scanner, &haystack[i..]) + i
+    }
+}
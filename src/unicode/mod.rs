@@ -0,0 +1,179 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! unicode module provides Unicode classes, as ranges of codepoints, and their UTF-8
+//! encoding into sequences of u8 ranges, from which [`crate::pattern::unicode`] builds
+//! [`crate::pattern::Pattern`]s.
+
+use regex_syntax::hir::{Class, ClassUnicode, ClassUnicodeRange, HirKind};
+use regex_syntax::utf8::Utf8Sequences;
+use regex_syntax::ParserBuilder;
+
+/// Returns the codepoint ranges of the Unicode general category or script named name,
+/// sorted and without overlaps, or None if there is no such class.
+///
+/// Names are those of `\p{..}` in the regex crate: short or long general category names
+/// (`L`, `Lu`, `Letter`, `Uppercase_Letter`…) and script names (`Greek`, `Latin`,
+/// `sc=Han`…), matched ignoring case, spaces, `_` and `-`.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::unicode::property;
+/// let nd = property("Nd").unwrap();
+/// assert_eq!(nd[0], ('0', '9'));
+/// let greek = property("Greek").unwrap();
+/// assert!(greek.iter().any(|(start, end)| (*start..=*end).contains(&'Ω')));
+/// assert_eq!(property("letter"), property("L"));
+/// assert!(property("Klingon").is_none());
+/// ```
+pub fn property(name: &str) -> Option<Vec<(char, char)>> {
+    let valid = |c: u8| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b' ' | b'=');
+    if name.is_empty() || !name.bytes().all(valid) {
+        return None;
+    }
+    let hir = ParserBuilder::new()
+        .build()
+        .parse(&format!(r"\p{{{}}}", name))
+        .ok()?;
+    match hir.kind() {
+        HirKind::Class(Class::Unicode(class)) => Some(
+            class
+                .ranges()
+                .iter()
+                .map(|r| (r.start(), r.end()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns ranges with the codepoints of every other case of their codepoints added,
+/// following Unicode simple case folding, as normalized ranges (see [`normalize`]).
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::unicode::case_fold;
+/// assert_eq!(case_fold(&[('a', 'c')]), vec![('A', 'C'), ('a', 'c')]);
+/// assert_eq!(case_fold(&[('é', 'é')]), vec![('É', 'É'), ('é', 'é')]);
+/// // K also folds to the Kelvin sign
+/// assert_eq!(case_fold(&[('k', 'k')]), vec![('K', 'K'), ('k', 'k'), ('\u{212A}', '\u{212A}')]);
+/// ```
+pub fn case_fold(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut class = ClassUnicode::new(
+        normalize(ranges)
+            .into_iter()
+            .map(|(start, end)| ClassUnicodeRange::new(start, end)),
+    );
+    class.case_fold_simple();
+    class
+        .ranges()
+        .iter()
+        .map(|r| (r.start(), r.end()))
+        .collect()
+}
+
+/// Returns ranges sorted, overlapping or adjacent ranges being merged. Empty ranges,
+/// whose start is greater than their end, are dropped.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::unicode::normalize;
+/// let r = normalize(&[('x', 'z'), ('a', 'c'), ('b', 'd'), ('e', 'e'), ('q', 'p')]);
+/// assert_eq!(r, vec![('a', 'e'), ('x', 'z')]);
+/// // surrogates are not codepoints
+/// let r = normalize(&[('\u{E000}', '\u{E00F}'), ('a', '\u{D7FF}')]);
+/// assert_eq!(r, vec![('a', '\u{E00F}')]);
+/// ```
+pub fn normalize(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut sorted: Vec<(char, char)> = ranges.iter().filter(|(s, e)| s <= e).copied().collect();
+    sorted.sort_unstable();
+    let mut merged: Vec<(char, char)> = Vec::with_capacity(sorted.len());
+    for (start, end) in sorted {
+        match merged.last_mut() {
+            Some(last) if start <= next(last.1).unwrap_or(char::MAX) => {
+                last.1 = last.1.max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Returns the codepoints not contained in ranges, as normalized ranges (see
+/// [`normalize`]).
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::unicode::complement;
+/// let r = complement(&[('b', 'y'), ('\0', 'a')]);
+/// assert_eq!(r, vec![('z', char::MAX)]);
+/// assert_eq!(complement(&r), vec![('\0', 'y')]);
+/// assert_eq!(complement(&[]), vec![('\0', char::MAX)]);
+/// ```
+pub fn complement(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut result = Vec::new();
+    let mut start = Some('\0');
+    for (low, high) in normalize(ranges) {
+        match start {
+            Some(s) if s < low => result.push((s, previous(low).unwrap())),
+            None => break,
+            _ => {}
+        }
+        start = next(high);
+    }
+    if let Some(s) = start {
+        result.push((s, char::MAX));
+    }
+    result
+}
+
+/// Returns the UTF-8 encodings of the codepoints of ranges as sequences of u8 ranges:
+/// a codepoint is contained in ranges if its encoding matches one sequence, each u8
+/// being in the range at the same position. Sequences are sorted, and two sequences
+/// either start with the same range or with disjoint ones.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::unicode::utf8_sequences;
+/// // à-ÿ is encoded as C3 A0 to C3 BF
+/// assert_eq!(utf8_sequences(&[('à', 'ÿ')]), vec![vec![(0xC3, 0xC3), (0xA0, 0xBF)]]);
+/// let s = utf8_sequences(&[('a', 'c'), ('\u{7F}', '\u{80}')]);
+/// assert_eq!(s, vec![vec![(b'a', b'c')], vec![(0x7F, 0x7F)], vec![(0xC2, 0xC2), (0x80, 0x80)]]);
+/// ```
+pub fn utf8_sequences(ranges: &[(char, char)]) -> Vec<Vec<(u8, u8)>> {
+    normalize(ranges)
+        .into_iter()
+        .flat_map(|(start, end)| Utf8Sequences::new(start, end))
+        .map(|s| s.as_slice().iter().map(|r| (r.start, r.end)).collect())
+        .collect()
+}
+
+/// Returns the codepoint following c, skipping surrogates.
+fn next(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        _ => char::from_u32(c as u32 + 1),
+    }
+}
+
+/// Returns the codepoint preceding c, skipping surrogates.
+fn previous(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        _ => char::from_u32((c as u32).checked_sub(1)?),
+    }
+}
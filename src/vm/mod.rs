@@ -0,0 +1,944 @@
+// Copyright (C) 2022 Laurent Wandrebeck
+//
+// This file is part of ripeg.
+//
+// ripeg is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// ripeg is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with ripeg.  If not, see <http://www.gnu.org/licenses/>.
+
+//! vm module provides the parsing virtual machine executing a [`Program`]
+//! against a subject.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use crate::capture::{self, Event, Node};
+use crate::charset::{NormalSet, Set};
+use crate::input::{Reader, ReaderAt};
+use crate::isa::{Instr, Program, IP};
+use crate::memo::{self, Table};
+use crate::scan::Scanner;
+
+/// Entry of the [`VM`] stack.
+enum Entry {
+    /// Pushed by [`Instr::Choice`] and Test* instructions: where to jump, which
+    /// subject position and number of capture events to restore when a failure happens.
+    Backtrack { ip: usize, sp: usize, caps: usize },
+    /// Pushed by [`Instr::Call`]: address of the instruction following the call,
+    /// subject position and address of the called rule.
+    Return { ip: usize, sp: usize, rule: usize },
+    /// Pushed by [`Instr::MemoOpen`]: memoization ID, subject position where the
+    /// memoized pattern starts, examined position of the enclosing pattern and number
+    /// of capture events before the memoized pattern.
+    Memo {
+        id: usize,
+        sp: usize,
+        examined: usize,
+        caps: usize,
+    },
+    /// Pushed by [`Instr::LeftCall`]: address of the instruction following the call,
+    /// subject position, address and [`Instr::Label`] ID of the called rule, number of
+    /// capture events before the call, and precedence and seed of the growth of the same
+    /// rule at the same position it interrupted, if any.
+    Grow {
+        ip: usize,
+        sp: usize,
+        rule: usize,
+        id: usize,
+        caps: usize,
+        outer: Option<(usize, memo::Entry)>,
+    },
+}
+
+/// Outcome of a [`VM::exec`] run.
+pub struct Match {
+    /// true if the subject has been accepted by the [`Program`].
+    pub matched: bool,
+    /// Number of u8 consumed from the subject. Always 0 when not matched.
+    pub length: usize,
+    /// Top level captures, in subject order. Always empty when not matched.
+    pub captures: Vec<Node>,
+    /// Why the subject has been rejected. Always None when matched.
+    pub failure: Option<Failure>,
+    /// Error labels thrown by [`Instr::Throw`], in subject order. When matched, all of
+    /// them have been recovered.
+    pub errors: Vec<Thrown>,
+}
+
+/// An error label thrown by [`Instr::Throw`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Thrown {
+    /// Error label.
+    pub label: usize,
+    /// Subject position at which the label has been thrown.
+    pub offset: usize,
+}
+
+/// Farthest failure of a [`VM`] run: the greatest subject position at which an
+/// instruction failed, and what was expected there.
+///
+/// # Examples
+/// ```
+/// use crate::ripeg::charset::Set;
+/// use crate::ripeg::re::parse;
+/// use crate::ripeg::vm::VM;
+/// let p = parse("
+///     List  <- Item (',' '\\n'? Item)* ';'
+///     Item  <- [a-z]+
+/// ").unwrap().compile().unwrap();
+/// let m = VM::new(&p).exec(b"ab,\ncd,\nef,;");
+/// let f = m.failure.unwrap();
+/// assert_eq!((f.offset, f.line, f.column), (11, 3, 4));
+/// assert!(f.chars.has(b'x') && f.chars.has(b'\n'));
+/// assert!(!f.chars.has(b';'));
+/// assert_eq!(f.rules, vec!["Item"]);
+/// assert_eq!(f.to_string(), "3:4: expected [\\x0aa-z] or Item");
+/// ```
+#[derive(Clone)]
+pub struct Failure {
+    /// Greatest subject position at which an instruction failed.
+    pub offset: usize,
+    /// Line of offset, starting at 1.
+    pub line: usize,
+    /// Column of offset in u8, starting at 1.
+    pub column: usize,
+    /// u8 expected at offset by the failing [`Instr::Char`], [`Instr::Set`],
+    /// [`Instr::Any`] and Test* instructions, and by the [`Instr::Span`] which stopped there.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::re::parse;
+    /// use crate::ripeg::vm::VM;
+    /// let p = parse("S <- 'a' [xy]* 'b'").unwrap().compile().unwrap();
+    /// let f = VM::new(&p).exec(b"axz").failure.unwrap();
+    /// assert_eq!(f.to_string(), "1:3: expected [bxy]");
+    /// ```
+    pub chars: NormalSet,
+    /// Names of the rules invoked at offset which failed, innermost first.
+    /// See [`Program::rule_name`].
+    pub rules: Vec<String>,
+    /// Error label thrown at offset without recovery rule, if the subject has been
+    /// rejected by [`Instr::Throw`]. chars and rules are then empty.
+    pub label: Option<usize>,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(label) = self.label {
+            return write!(f, "{}:{}: error label {}", self.line, self.column, label);
+        }
+        let mut expected = Vec::new();
+        if self.chars.size() > 0 {
+            expected.push(self.chars.string());
+        }
+        expected.extend(self.rules.iter().cloned());
+        if expected.is_empty() {
+            write!(f, "{}:{}: unexpected input", self.line, self.column)
+        } else {
+            write!(
+                f,
+                "{}:{}: expected {}",
+                self.line,
+                self.column,
+                expected.join(" or ")
+            )
+        }
+    }
+}
+
+/// Farthest failure being tracked by a [`VM`] run.
+struct Farthest {
+    /// Greatest subject position at which an instruction failed.
+    offset: usize,
+    /// u8 expected at offset.
+    chars: NormalSet,
+    /// Addresses of the rules invoked at offset which failed.
+    rules: Vec<usize>,
+    /// Error label thrown at offset without recovery rule.
+    label: Option<usize>,
+}
+
+impl Farthest {
+    /// Instanciate a [`Farthest`] failure at position 0 expecting nothing.
+    fn new() -> Self {
+        Self {
+            offset: 0,
+            chars: NormalSet::new(&[]),
+            rules: Vec::new(),
+            label: None,
+        }
+    }
+
+    /// Records that the u8 of set were expected at position sp. Returns false if sp
+    /// is before the farthest failure, set being ignored.
+    fn expect(&mut self, sp: usize, set: &NormalSet) -> bool {
+        if sp > self.offset {
+            *self = Self::new();
+            self.offset = sp;
+        }
+        if sp == self.offset {
+            self.chars |= set;
+        }
+        sp == self.offset
+    }
+
+    /// Records that the rule at address rule, invoked at position sp, failed.
+    fn expect_rule(&mut self, sp: usize, rule: usize) {
+        if self.expect(sp, &NormalSet::new(&[])) && !self.rules.contains(&rule) {
+            self.rules.push(rule);
+        }
+    }
+}
+
+/// Parsing virtual machine.
+///
+/// Executes a [`Program`] against a subject, starting at the first instruction
+/// and position 0 of the subject, until [`Instr::End`] or [`Instr::EndFail`] is
+/// reached, or until a failure happens with an empty backtrack stack.
+pub struct VM<'a> {
+    /// Program being executed.
+    program: &'a Program,
+    /// Backtrack and return stack.
+    stack: Vec<Entry>,
+    /// Capture events of the current run.
+    events: Vec<Event>,
+    /// Farthest failure of the current run.
+    farthest: Farthest,
+    /// Seeds of the left-recursive rules being grown, keyed by [`Instr::Label`] ID and
+    /// subject position.
+    seeds: Table,
+    /// Precedence of the left-recursive rules being grown, keyed like seeds.
+    growing: HashMap<(usize, usize), usize>,
+    /// [`Scanner`] of each [`Instr::Span`], indexed like the instructions of the program.
+    scanners: Vec<Option<Scanner>>,
+}
+
+/// Methods for a [`VM`]
+impl<'a> VM<'a> {
+    /// Instanciate a new [`VM`] running the given [`Program`]
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// use crate::ripeg::vm::VM;
+    /// let p = Program::from(vec![Instr::End]);
+    /// let mut vm = VM::new(&p);
+    /// assert!(vm.exec(b"").matched);
+    /// ```
+    pub fn new(program: &'a Program) -> Self {
+        Self {
+            program,
+            stack: Vec::new(),
+            events: Vec::new(),
+            farthest: Farthest::new(),
+            seeds: Table::new(),
+            growing: HashMap::new(),
+            scanners: (0..program.len())
+                .map(|k| match program.get(k) {
+                    Some(Instr::Span(s)) => Some(Scanner::new(s)),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Runs the [`Program`] against subject and returns the [`Match`] outcome.
+    ///
+    /// The [`VM`] can be reused to run the same [`Program`] on several subjects.
+    /// When the subject is rejected, [`Match::failure`] tells where and why.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`Program`] is malformed: jump to an index out of the
+    /// [`Program`], [`Instr::Commit`] without backtrack entry on top of the stack,
+    /// [`Instr::Return`] without return address on top of the stack.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::charset::{NormalSet, Set};
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// use crate::ripeg::vm::VM;
+    /// // 'a' / [0-9]+
+    /// let p = Program::from(vec![
+    ///     Instr::Choice(3),
+    ///     Instr::Char(b'a'),
+    ///     Instr::Commit(5),
+    ///     Instr::Set(NormalSet::range(b'0', b'9')),
+    ///     Instr::Span(NormalSet::range(b'0', b'9')),
+    ///     Instr::End,
+    /// ]);
+    /// let mut vm = VM::new(&p);
+    /// let m = vm.exec(b"abc");
+    /// assert!(m.matched);
+    /// assert_eq!(m.length, 1);
+    /// let m = vm.exec(b"0123x");
+    /// assert!(m.matched);
+    /// assert_eq!(m.length, 4);
+    /// let m = vm.exec(b"x");
+    /// assert!(!m.matched);
+    /// assert_eq!(m.length, 0);
+    /// let f = m.failure.unwrap();
+    /// assert_eq!(f.offset, 0);
+    /// assert!(f.chars.has(b'a') && f.chars.has(b'5') && !f.chars.has(b'x'));
+    /// ```
+    ///
+    /// Predicates and non-terminals:
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// use crate::ripeg::vm::VM;
+    /// // S <- !'a' A ; A <- .
+    /// let p = Program::from(vec![
+    ///     Instr::Choice(3),
+    ///     Instr::Char(b'a'),
+    ///     Instr::FailTwice,
+    ///     Instr::Call(5),
+    ///     Instr::End,
+    ///     Instr::Any(1),
+    ///     Instr::Return,
+    /// ]);
+    /// let mut vm = VM::new(&p);
+    /// assert!(!vm.exec(b"ab").matched);
+    /// let m = vm.exec(b"ba");
+    /// assert!(m.matched);
+    /// assert_eq!(m.length, 1);
+    /// assert!(!vm.exec(b"").matched);
+    /// ```
+    ///
+    /// Counts running past the largest position fail like those running past the subject:
+    /// ```
+    /// use crate::ripeg::isa::{Instr, Program};
+    /// use crate::ripeg::vm::VM;
+    /// let p = Program::from(vec![Instr::Char(b'a'), Instr::Any(usize::MAX), Instr::End]);
+    /// assert!(!VM::new(&p).exec(b"ab").matched);
+    /// let p = Program::from(vec![
+    ///     Instr::Char(b'a'),
+    ///     Instr::TestAny(usize::MAX, 3),
+    ///     Instr::End,
+    ///     Instr::Any(1),
+    ///     Instr::End,
+    /// ]);
+    /// assert!(p.verify().is_ok());
+    /// assert_eq!(VM::new(&p).exec(b"ab").length, 2);
+    /// ```
+    pub fn exec(&mut self, subject: &[u8]) -> Match {
+        self.exec_memo(subject, &mut Table::new())
+    }
+
+    /// Runs the [`Program`] against subject like [`VM::exec`], using and filling table
+    /// when executing [`Instr::MemoOpen`] and [`Instr::MemoClose`].
+    ///
+    /// Keeping the same table between runs on the same subject avoids matching again
+    /// memoized patterns, which guarantees a linear time on backtracking grammars.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`Program`] is malformed, see [`VM::exec`], or if
+    /// [`Instr::MemoClose`] finds no memoization entry on top of the stack.
+    ///
+    /// # Examples
+    /// ```
+    /// use crate::ripeg::memo::Table;
+    /// use crate::ripeg::re::parse;
+    /// use crate::ripeg::vm::VM;
+    /// let mut g = parse("
+    ///     S <- A 'x' / A 'y' / A
+    ///     A <- 'a' A / 'a'
+    /// ").unwrap();
+    /// g.memoize(true);
+    /// let p = g.compile().unwrap();
+    /// let mut vm = VM::new(&p);
+    /// let mut table = Table::new();
+    /// assert_eq!(vm.exec_memo(b"aaaay", &mut table).length, 5);
+    /// // A has been memoized at positions 0 to 3.
+    /// assert_eq!(table.get(1, 0).unwrap().length, Some(4));
+    /// assert_eq!(table.get(1, 3).unwrap().length, Some(1));
+    /// assert_eq!(table.get(1, 4).unwrap().length, None);
+    /// // A examined the 'y' at position 4 when invoked at position 0.
+    /// assert_eq!(table.get(1, 0).unwrap().examined, 5);
+    /// assert_eq!(vm.exec_memo(b"aaaay", &mut table).length, 5);
+    /// ```
+    ///
+    /// Captures made by a memoized rule are restored when the rule is skipped:
+    /// ```
+    /// use crate::ripeg::charset::NormalSet;
+    /// use crate::ripeg::grammar::Grammar;
+    /// use crate::ripeg::memo::Table;
+    /// use crate::ripeg::pattern::{capture, class, concat, literal, non_term, or, plus};
+    /// use crate::ripeg::vm::VM;
+    /// // S <- N '+' N / N ; N <- {[0-9]+}
+    /// let mut g = Grammar::new("S");
+    /// g.add("S", or(vec![concat(vec![non_term("N"), literal("+"), non_term("N")]), non_term("N")]));
+    /// g.add("N", capture(7, plus(class(NormalSet::range(b'0', b'9')))));
+    /// g.memoize(true);
+    /// let p = g.compile().unwrap();
+    /// let mut vm = VM::new(&p);
+    /// let mut table = Table::new();
+    /// let m = vm.exec_memo(b"42-1", &mut table);
+    /// assert_eq!(m.length, 2);
+    /// assert_eq!(m.captures.len(), 1);
+    /// assert_eq!((m.captures[0].id, m.captures[0].start, m.captures[0].end), (7, 0, 2));
+    /// let again = vm.exec_memo(b"42-1", &mut table);
+    /// assert_eq!(again.captures, m.captures);
+    /// ```
+    pub fn exec_memo(&mut self, mut subject: &[u8], table: &mut Table) -> Match {
+        self.exec_input(&mut subject, table)
+            .expect("reading a slice never fails")
+    }
+
+    /// Runs the [`Program`] against a subject read through [`ReaderAt`] like
+    /// [`VM::exec_memo`], so that the subject does not need to be contiguous in memory.
+    ///
+    /// Returns the error of the first failing [`ReaderAt::read_at`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`Program`] is malformed, see [`VM::exec_memo`].
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Cursor;
+    /// use crate::ripeg::input::{Chunks, Seekable};
+    /// use crate::ripeg::memo::Table;
+    /// use crate::ripeg::re::parse;
+    /// use crate::ripeg::vm::VM;
+    /// let p = parse("Words <- [a-z]+ (' ' [a-z]+)*").unwrap().compile().unwrap();
+    /// let mut vm = VM::new(&p);
+    /// let mut rope = Chunks::new(vec!["hello w", "or", "ld!"]);
+    /// assert_eq!(vm.exec_input(&mut rope, &mut Table::new()).unwrap().length, 11);
+    /// let mut file = Seekable::new(Cursor::new("hello world!"));
+    /// assert_eq!(vm.exec_input(&mut file, &mut Table::new()).unwrap().length, 11);
+    /// let mut text = String::from("hello world!");
+    /// assert_eq!(vm.exec_input(&mut text, &mut Table::new()).unwrap().length, 11);
+    /// ```
+    pub fn exec_input<R: ReaderAt + ?Sized>(
+        &mut self,
+        input: &mut R,
+        table: &mut Table,
+    ) -> io::Result<Match> {
+        let mut subject = Reader::new(input);
+        self.stack.clear();
+        self.events.clear();
+        self.farthest = Farthest::new();
+        self.seeds.clear();
+        self.growing.clear();
+        let mut ip = IP::Index(0);
+        let mut sp = 0usize;
+        // end of the subject part examined since the innermost memoization entry was pushed.
+        let mut examined = 0usize;
+        loop {
+            let i = match ip {
+                IP::Index(i) => i,
+                IP::None => {
+                    // unwind the stack up to the first backtrack entry.
+                    ip = loop {
+                        match self.stack.pop() {
+                            Some(Entry::Backtrack {
+                                ip: bip,
+                                sp: bsp,
+                                caps,
+                            }) => {
+                                sp = bsp;
+                                self.events.truncate(caps);
+                                break IP::Index(bip);
+                            }
+                            Some(Entry::Return { sp: rsp, rule, .. }) => {
+                                self.farthest.expect_rule(rsp, rule);
+                            }
+                            Some(Entry::Memo {
+                                id,
+                                sp: msp,
+                                examined: outer,
+                                ..
+                            }) => {
+                                let entry = memo::Entry {
+                                    length: None,
+                                    examined: examined - msp,
+                                    captures: Vec::new(),
+                                    errors: Vec::new(),
+                                };
+                                table.insert(id, msp, entry);
+                                examined = examined.max(outer);
+                            }
+                            Some(Entry::Grow {
+                                ip: gip,
+                                sp: gsp,
+                                rule,
+                                id,
+                                caps,
+                                outer,
+                            }) => match self.end_growth(id, gsp, caps, outer) {
+                                Some(end) => {
+                                    sp = end;
+                                    break IP::Index(gip);
+                                }
+                                None => self.farthest.expect_rule(gsp, rule),
+                            },
+                            None => return self.failure(&mut subject),
+                        }
+                    };
+                    continue;
+                }
+            };
+            let instr = match self.program.get(i) {
+                Some(instr) => instr,
+                None => panic!("ip {} is out of the program", i),
+            };
+            ip = match instr {
+                Instr::Any(n) => {
+                    // n u8 past usize::MAX cannot be in the subject.
+                    let end = sp.checked_add(*n);
+                    examined = examined.max(end.unwrap_or(usize::MAX));
+                    match end {
+                        Some(end) if *n == 0 || subject.get(end - 1)?.is_some() => {
+                            sp = end;
+                            IP::Index(i + 1)
+                        }
+                        _ => {
+                            self.farthest.expect(sp, &NormalSet::range(0, 255));
+                            IP::None
+                        }
+                    }
+                }
+                Instr::BackCommit(l) => {
+                    sp = self.pop_backtrack(i);
+                    IP::Index(*l)
+                }
+                Instr::Call(l) => {
+                    self.stack.push(Entry::Return {
+                        ip: i + 1,
+                        sp,
+                        rule: *l,
+                    });
+                    IP::Index(*l)
+                }
+                Instr::CaptureBegin(id) => {
+                    self.events.push(Event::Open(*id, sp));
+                    IP::Index(i + 1)
+                }
+                Instr::CaptureEnd => {
+                    self.events.push(Event::Close(sp));
+                    IP::Index(i + 1)
+                }
+                Instr::Char(c) => {
+                    examined = examined.max(sp + 1);
+                    if subject.get(sp)? == Some(*c) {
+                        sp += 1;
+                        IP::Index(i + 1)
+                    } else {
+                        self.farthest.expect(sp, &NormalSet::new(&[*c]));
+                        IP::None
+                    }
+                }
+                Instr::Choice(l) => {
+                    self.stack.push(Entry::Backtrack {
+                        ip: *l,
+                        sp,
+                        caps: self.events.len(),
+                    });
+                    IP::Index(i + 1)
+                }
+                Instr::Commit(l) => {
+                    self.pop_backtrack(i);
+                    IP::Index(*l)
+                }
+                Instr::End => {
+                    return Ok(Match {
+                        matched: true,
+                        length: sp,
+                        captures: capture::build(&self.events),
+                        failure: None,
+                        errors: self.errors(),
+                    });
+                }
+                Instr::EndFail => return self.failure(&mut subject),
+                Instr::Fail => IP::None,
+                Instr::FailTwice => {
+                    self.pop_backtrack(i);
+                    IP::None
+                }
+                Instr::Jump(l) => IP::Index(*l),
+                Instr::Label(_) | Instr::Nop => IP::Index(i + 1),
+                Instr::LeftCall(l, k) => {
+                    let id = match self.program.get(*l) {
+                        Some(Instr::Label(id)) => *id,
+                        _ => panic!("LeftCall at {} does not target a Label", i),
+                    };
+                    match self.growing.get(&(id, sp)).copied() {
+                        Some(prec) if *k < prec => IP::None,
+                        Some(prec) if *k == prec => match self.seeds.get(id, sp) {
+                            Some(memo::Entry {
+                                length: Some(n),
+                                captures,
+                                errors,
+                                ..
+                            }) => {
+                                for node in captures {
+                                    self.events.push(Event::Node(node.moved(0, sp)));
+                                }
+                                for e in errors {
+                                    self.events.push(Event::Throw(e.label, e.offset + sp));
+                                }
+                                sp += *n;
+                                IP::Index(i + 1)
+                            }
+                            _ => IP::None,
+                        },
+                        prec => {
+                            // the seed of a new growth is a failure.
+                            let seed = memo::Entry {
+                                length: None,
+                                examined: 0,
+                                captures: Vec::new(),
+                                errors: Vec::new(),
+                            };
+                            let outer = match prec {
+                                Some(prec) => self.seeds.remove(id, sp).map(|e| (prec, e)),
+                                None => None,
+                            };
+                            self.seeds.insert(id, sp, seed);
+                            self.growing.insert((id, sp), *k);
+                            self.stack.push(Entry::Grow {
+                                ip: i + 1,
+                                sp,
+                                rule: *l,
+                                id,
+                                caps: self.events.len(),
+                                outer,
+                            });
+                            IP::Index(*l)
+                        }
+                    }
+                }
+                Instr::MemoClose => match self.stack.pop() {
+                    Some(Entry::Memo {
+                        id,
+                        sp: msp,
+                        examined: outer,
+                        caps,
+                    }) => {
+                        let entry = self.entry(msp, sp, examined, caps);
+                        table.insert(id, msp, entry);
+                        examined = examined.max(outer);
+                        IP::Index(i + 1)
+                    }
+                    _ => panic!("MemoClose at {} without memoization entry", i),
+                },
+                Instr::MemoOpen(l, id) => match table.get(*id, sp) {
+                    Some(entry) => {
+                        examined = examined.max(sp + entry.examined);
+                        match entry.length {
+                            Some(n) => {
+                                for node in &entry.captures {
+                                    self.events.push(Event::Node(node.moved(0, sp)));
+                                }
+                                for e in &entry.errors {
+                                    self.events.push(Event::Throw(e.label, e.offset + sp));
+                                }
+                                sp += n;
+                                IP::Index(*l)
+                            }
+                            None => IP::None,
+                        }
+                    }
+                    None => {
+                        self.stack.push(Entry::Memo {
+                            id: *id,
+                            sp,
+                            examined,
+                            caps: self.events.len(),
+                        });
+                        examined = sp;
+                        IP::Index(i + 1)
+                    }
+                },
+                Instr::PartialCommit(l) => {
+                    match self.stack.last_mut() {
+                        Some(Entry::Backtrack { sp: bsp, caps, .. }) => {
+                            *bsp = sp;
+                            *caps = self.events.len();
+                        }
+                        _ => panic!("PartialCommit at {} without backtrack entry", i),
+                    }
+                    IP::Index(*l)
+                }
+                Instr::Return => match self.stack.pop() {
+                    Some(Entry::Return { ip: r, .. }) => IP::Index(r),
+                    Some(Entry::Grow {
+                        ip: r,
+                        sp: gsp,
+                        rule,
+                        id,
+                        caps,
+                        outer,
+                    }) => {
+                        let seed = self.seeds.get(id, gsp).and_then(|e| e.length);
+                        if seed < Some(sp - gsp) {
+                            // the match grew: it is the new seed, match the rule again.
+                            let entry = self.entry(gsp, sp, sp, caps);
+                            self.seeds.insert(id, gsp, entry);
+                            self.events.truncate(caps);
+                            sp = gsp;
+                            self.stack.push(Entry::Grow {
+                                ip: r,
+                                sp: gsp,
+                                rule,
+                                id,
+                                caps,
+                                outer,
+                            });
+                            IP::Index(rule)
+                        } else {
+                            sp = self
+                                .end_growth(id, gsp, caps, outer)
+                                .expect("a grown seed is a match");
+                            IP::Index(r)
+                        }
+                    }
+                    _ => panic!("Return at {} without return address", i),
+                },
+                Instr::Set(s) => {
+                    examined = examined.max(sp + 1);
+                    match subject.get(sp)? {
+                        Some(c) if s.has(c) => {
+                            sp += 1;
+                            IP::Index(i + 1)
+                        }
+                        _ => {
+                            self.farthest.expect(sp, s);
+                            IP::None
+                        }
+                    }
+                }
+                Instr::Span(s) => {
+                    let scanner = self.scanners[i].as_ref().expect("Span has a Scanner");
+                    loop {
+                        let window = subject.window(sp)?;
+                        let n = scanner.span(window);
+                        sp += n;
+                        if n < window.len() || window.is_empty() {
+                            break;
+                        }
+                    }
+                    examined = examined.max(sp + 1);
+                    // The loop could have gone on with any char of s.
+                    self.farthest.expect(sp, s);
+                    IP::Index(i + 1)
+                }
+                Instr::TestAny(n, l) => {
+                    let end = sp.checked_add(*n);
+                    examined = examined.max(end.unwrap_or(usize::MAX));
+                    match end {
+                        Some(end) if *n == 0 || subject.get(end - 1)?.is_some() => {
+                            self.stack.push(Entry::Backtrack {
+                                ip: *l,
+                                sp,
+                                caps: self.events.len(),
+                            });
+                            sp = end;
+                            IP::Index(i + 1)
+                        }
+                        _ => {
+                            self.farthest.expect(sp, &NormalSet::range(0, 255));
+                            IP::Index(*l)
+                        }
+                    }
+                }
+                Instr::TestChar(c, l) => {
+                    examined = examined.max(sp + 1);
+                    if subject.get(sp)? == Some(*c) {
+                        self.stack.push(Entry::Backtrack {
+                            ip: *l,
+                            sp,
+                            caps: self.events.len(),
+                        });
+                        sp += 1;
+                        IP::Index(i + 1)
+                    } else {
+                        self.farthest.expect(sp, &NormalSet::new(&[*c]));
+                        IP::Index(*l)
+                    }
+                }
+                Instr::TestCharNoChoice(c, l) => {
+                    examined = examined.max(sp + 1);
+                    if subject.get(sp)? == Some(*c) {
+                        sp += 1;
+                        IP::Index(i + 1)
+                    } else {
+                        self.farthest.expect(sp, &NormalSet::new(&[*c]));
+                        IP::Index(*l)
+                    }
+                }
+                Instr::TestSet(s, l) => {
+                    examined = examined.max(sp + 1);
+                    match subject.get(sp)? {
+                        Some(c) if s.has(c) => {
+                            self.stack.push(Entry::Backtrack {
+                                ip: *l,
+                                sp,
+                                caps: self.events.len(),
+                            });
+                            sp += 1;
+                            IP::Index(i + 1)
+                        }
+                        _ => {
+                            self.farthest.expect(sp, s);
+                            IP::Index(*l)
+                        }
+                    }
+                }
+                Instr::Throw(label) => {
+                    self.events.push(Event::Throw(*label, sp));
+                    match self.program.recovery(*label) {
+                        Some(rule) => {
+                            self.stack.push(Entry::Return {
+                                ip: i + 1,
+                                sp,
+                                rule,
+                            });
+                            IP::Index(rule)
+                        }
+                        None => {
+                            self.farthest = Farthest::new();
+                            self.farthest.offset = sp;
+                            self.farthest.label = Some(*label);
+                            return self.failure(&mut subject);
+                        }
+                    }
+                }
+                Instr::TestSetNoChoice(s, l) => {
+                    examined = examined.max(sp + 1);
+                    match subject.get(sp)? {
+                        Some(c) if s.has(c) => {
+                            sp += 1;
+                            IP::Index(i + 1)
+                        }
+                        _ => {
+                            self.farthest.expect(sp, s);
+                            IP::Index(*l)
+                        }
+                    }
+                }
+            };
+        }
+    }
+
+    /// Returns the [`Match`] of a rejected subject, locating the farthest failure in it.
+    fn failure<R: ReaderAt + ?Sized>(&self, subject: &mut Reader<R>) -> io::Result<Match> {
+        let offset = self.farthest.offset;
+        let (mut line, mut line_start) = (1, 0);
+        for pos in 0..offset {
+            if subject.get(pos)? == Some(b'\n') {
+                line += 1;
+                line_start = pos + 1;
+            }
+        }
+        let rules = self
+            .farthest
+            .rules
+            .iter()
+            .filter_map(|rule| match self.program.get(*rule) {
+                Some(Instr::Label(id)) => self.program.rule_name(*id),
+                _ => None,
+            })
+            .map(str::to_owned)
+            .collect();
+        Ok(Match {
+            matched: false,
+            length: 0,
+            captures: Vec::new(),
+            failure: Some(Failure {
+                offset,
+                line,
+                column: offset - line_start + 1,
+                chars: self.farthest.chars,
+                rules,
+                label: self.farthest.label,
+            }),
+            errors: self.errors(),
+        })
+    }
+
+    /// Returns the [`memo::Entry`] of a pattern which matched from start to end, examining
+    /// the subject up to examined, its capture events starting at caps.
+    fn entry(&self, start: usize, end: usize, examined: usize, caps: usize) -> memo::Entry {
+        let captures = capture::build(&self.events[caps..]);
+        let errors = self.events[caps..].iter().filter_map(|e| match e {
+            Event::Throw(label, pos) => Some(Thrown {
+                label: *label,
+                offset: pos - start,
+            }),
+            _ => None,
+        });
+        memo::Entry {
+            length: Some(end - start),
+            examined: examined - start,
+            captures: captures.iter().map(|n| n.moved(start, 0)).collect(),
+            errors: errors.collect(),
+        }
+    }
+
+    /// Ends the growth of the left-recursive rule of [`Instr::Label`] ID id at position sp,
+    /// restoring the growth it interrupted, if any. Replaces the capture events from caps
+    /// on by those of the seed, and returns the end of the seed if it is a match.
+    fn end_growth(
+        &mut self,
+        id: usize,
+        sp: usize,
+        caps: usize,
+        outer: Option<(usize, memo::Entry)>,
+    ) -> Option<usize> {
+        let seed = self.seeds.remove(id, sp);
+        match outer {
+            Some((prec, entry)) => {
+                self.seeds.insert(id, sp, entry);
+                self.growing.insert((id, sp), prec);
+            }
+            None => {
+                self.growing.remove(&(id, sp));
+            }
+        }
+        self.events.truncate(caps);
+        let seed = seed?;
+        let n = seed.length?;
+        for node in &seed.captures {
+            self.events.push(Event::Node(node.moved(0, sp)));
+        }
+        for e in &seed.errors {
+            self.events.push(Event::Throw(e.label, e.offset + sp));
+        }
+        Some(sp + n)
+    }
+
+    /// Returns the error labels thrown by the current run.
+    fn errors(&self) -> Vec<Thrown> {
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Throw(label, pos) => Some(Thrown {
+                    label: *label,
+                    offset: *pos,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Pops the backtrack entry on top of the stack and returns its subject position.
+    /// i is the index of the instruction requiring the pop, used in the panic message.
+    fn pop_backtrack(&mut self, i: usize) -> usize {
+        match self.stack.pop() {
+            Some(Entry::Backtrack { sp, .. }) => sp,
+            _ => panic!("instruction at {} expects a backtrack entry", i),
+        }
+    }
+}